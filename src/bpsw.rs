@@ -0,0 +1,175 @@
+//! The Baillie–PSW probable prime test.
+//!
+//! A number passes when it is a strong probable prime to base 2 and a
+//! strong Lucas probable prime with the parameters chosen by Selfridge's
+//! method A: D is the first of 5, −7, 9, −11, ... with Jacobi symbol
+//! (D/n) = −1, P = 1 and Q = (1 − D)/4. The two tests fail on very
+//! different sets of composites, and no composite passing both is known,
+//! although none has been proven not to exist above 2⁶⁴.
+//!
+//! Reference: <https://en.wikipedia.org/wiki/Baillie%E2%80%93PSW_primality_test>
+//! Reference: <https://en.wikipedia.org/wiki/Lucas_pseudoprime#Strong_Lucas_pseudoprimes>
+use crate::candidates::miller_rabin_witness;
+use crate::montgomery::{Montgomery, MontgomeryArith};
+use crate::modular::jacobi_symbol;
+use crate::PrimeInt;
+
+/// Test `n` with the Baillie–PSW test. Returns `true` when `n` is a prime
+/// or a BPSW pseudoprime, and `false` when `n` is proven composite.
+#[must_use]
+pub fn bpsw(n: u128) -> bool {
+    if n < 4 {
+        return n >= 2;
+    }
+    let Some(mont) = Montgomery::new(n) else {
+        return false;
+    };
+    is_probable_prime(&mont)
+}
+
+/// Run the Baillie–PSW test on the odd modulus of `mont`, which must be at
+/// least 5.
+pub(crate) fn is_probable_prime<M: MontgomeryArith>(mont: &M) -> bool {
+    strong_base2(mont) && strong_lucas(mont)
+}
+
+/// The strong probable prime test to base 2.
+fn strong_base2<M: MontgomeryArith>(mont: &M) -> bool {
+    strong_probable_prime(mont, M::Int::from(2))
+}
+
+/// The strong probable prime (Miller–Rabin) test to the given base, which
+/// must be below the odd modulus of `mont`.
+pub(crate) fn strong_probable_prime<M: MontgomeryArith>(mont: &M, base: M::Int) -> bool {
+    let n_minus_1 = mont.modulus() - M::Int::ONE;
+    let r = n_minus_1.trailing_zeros();
+    let x = mont.pow(mont.to_montgomery(base), n_minus_1 >> r);
+    let minus_one = mont.sub(M::Int::ZERO, mont.one());
+    miller_rabin_witness(x, mont.one(), minus_one, r, |x| mont.mul(x, x))
+}
+
+/// The strong Lucas probable prime test with Selfridge's parameters.
+/// With n + 1 = k·2^s, n passes when U_k ≡ 0 or V_(k·2^r) ≡ 0 (mod n) for
+/// some 0 ≤ r < s.
+fn strong_lucas<M: MontgomeryArith>(mont: &M) -> bool {
+    let (zero, one) = (M::Int::ZERO, M::Int::ONE);
+    let n = mont.modulus();
+    // No suitable D exists for a perfect square.
+    let root = n.isqrt();
+    if root * root == n {
+        return false;
+    }
+    let mut d: i128 = 5;
+    loop {
+        match jacobi(d, n) {
+            -1 => break,
+            // D shares a factor with n
+            0 if M::Int::from_u128(d.unsigned_abs()) < n => return false,
+            _ => d = if d > 0 { -d - 2 } else { -d + 2 },
+        }
+    }
+    let to_mont = |x: i128| {
+        let r = M::Int::from_u128(x.unsigned_abs()) % n;
+        mont.to_montgomery(if x < 0 && r > zero { n - r } else { r })
+    };
+    let (dm, q) = (to_mont(d), to_mont((1 - d) / 4));
+    // Halving commutes with the Montgomery representation.
+    let half = |x: M::Int| {
+        if x & one == zero { x >> 1 } else { (x >> 1) + (n >> 1) + one }
+    };
+    // n + 1 = k·2^s, without overflowing when n is the largest value.
+    let (k, s) = match n.checked_add(one) {
+        Some(m) => (m >> m.trailing_zeros(), m.trailing_zeros()),
+        None => (one, M::Int::BITS),
+    };
+    // Walk the bits of k from the top, keeping U_i, V_i and Q^i, with
+    // P = 1: U_2i = U_i·V_i, V_2i = V_i² − 2Q^i, and for the odd step
+    // U_(i+1) = (U_i + V_i)/2, V_(i+1) = (D·U_i + V_i)/2.
+    let (mut u, mut v, mut qk) = (mont.one(), mont.one(), q);
+    for bit in (0..M::Int::BITS - 1 - k.leading_zeros()).rev() {
+        u = mont.mul(u, v);
+        v = mont.sub(mont.mul(v, v), mont.add(qk, qk));
+        qk = mont.mul(qk, qk);
+        if (k >> bit) & one == one {
+            (u, v) = (half(mont.add(u, v)), half(mont.add(mont.mul(dm, u), v)));
+            qk = mont.mul(qk, q);
+        }
+    }
+    if u == zero || v == zero {
+        return true;
+    }
+    for _ in 1..s {
+        v = mont.sub(mont.mul(v, v), mont.add(qk, qk));
+        if v == zero {
+            return true;
+        }
+        qk = mont.mul(qk, qk);
+    }
+    false
+}
+
+/// The Jacobi symbol (a/n) for odd n and signed a.
+fn jacobi<T: PrimeInt>(a: i128, n: T) -> i8 {
+    let r = T::from_u128(a.unsigned_abs()) % n;
+    jacobi_symbol(if a < 0 && r > T::ZERO { n - r } else { r }, n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bpsw, jacobi, strong_probable_prime};
+    use crate::montgomery::Montgomery;
+    use crate::u128_is_prime;
+
+    #[test]
+    fn test_jacobi() {
+        // (a/15) for a = 0..15
+        let expected = [0, 1, 1, 0, 1, 0, 0, -1, 1, 0, 0, -1, 0, -1, -1];
+        for (a, &j) in expected.iter().enumerate() {
+            assert_eq!(jacobi(a as i128, 15u128), j, "({a}/15)");
+        }
+        assert_eq!(jacobi(-1, 7u64), -1);
+        assert_eq!(jacobi(-7, 11u32), 1);
+        assert_eq!(jacobi(5, 1u128), 1);
+    }
+
+    #[test]
+    fn test_bpsw_matches_is_prime() {
+        for n in 0..100_000u128 {
+            assert_eq!(bpsw(n), u128_is_prime(n), "bpsw({n})");
+        }
+        // Strong pseudoprimes to base 2 and strong Lucas pseudoprimes
+        let pseudoprimes: [u128; 8] = [2047, 3277, 4033, 3215031751, 5459, 5777, 10877, 16109];
+        for n in pseudoprimes {
+            assert!(!bpsw(n), "{n} is composite");
+        }
+    }
+
+    #[test]
+    fn test_strong_probable_prime() {
+        // 2047 = 23 · 89 is a strong pseudoprime to base 2 but not to base 3
+        let mont = Montgomery::new(2047).unwrap();
+        assert!(strong_probable_prime(&mont, 2));
+        assert!(!strong_probable_prime(&mont, 3));
+        let mont = Montgomery::new((1 << 127) - 1).unwrap();
+        assert!([2, 3, 5, 37].iter().all(|&a| strong_probable_prime(&mont, a)));
+    }
+
+    #[test]
+    fn test_bpsw_large() {
+        let primes: [u128; 4] = [
+            18446744073709551557,
+            1267650600228229401496703205361,
+            85070591730234615865843651857942052727,
+            (1 << 127) - 1,
+        ];
+        for p in primes {
+            assert!(bpsw(p), "{p} is prime");
+        }
+        // A strong pseudoprime to all prime bases up to 37, the square of a
+        // prime and a product of two nearly equal primes
+        assert!(!bpsw(3317044064679887385961981));
+        assert!(!bpsw(9223372036854775837 * 9223372036854775837));
+        assert!(!bpsw(18446744073709551557 * 18446744073709551533));
+        assert!(!bpsw(u128::MAX));
+    }
+}
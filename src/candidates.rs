@@ -9,6 +9,10 @@
 //! In `factorize`, the iterator is only consumed up to sqrt(n), which for
 //! the maximum u128 value is approximately 1.84e19.
 //!
+use crate::modular::{mod_mul, mod_pow};
+use crate::montgomery::Montgomery;
+use crate::PrimeInt;
+
 /// Wheel factorization algorithm with base {2, 3, 5} (30 spokes).
 ///
 /// This is an infinite iterator; callers must provide a termination condition.
@@ -56,65 +60,91 @@ impl Iterator for PrimeWheel30 {
 
 /// Wheel factorization algorithm with base {2, 3, 5, 7} (210 spokes).
 ///
-/// This is an infinite iterator; callers must provide a termination condition.
-/// It is designed for use in trial division up to √n.
+/// The iterator ends at the last candidate that fits in the type, so over
+/// `u128` it runs up to the top of the range rather than forever; callers
+/// still provide their own termination condition. It is designed for use in
+/// trial division up to √n.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
-pub struct PrimeWheel210 {
-    base: u128,
-    index: usize,
+pub struct Wheel210<T> {
+    pub(crate) base: T,
+    pub(crate) index: usize,
 }
 
-impl PrimeWheel210 {
-    const GAPS: [u128; 53] = [
-        2, 1, 2, 2, 4, // initial phase: 2, 3, 5, 7, 11 (index 0-4)
-        2, 4, 2, 4, 6, 2, 6, 4, 2, 4, 6, 6, 2, 6, 4, // 13..71 (index 5, start of cycle)
-        2, 6, 4, 6, 8, 4, 2, 4, 2, 4, 8, 6, 4, 6, 2, 4, // 73..143
-        6, 2, 6, 6, 4, 2, 4, 6, 2, 6, 4, 2, 4, 2, 10, 2, // 149..211
-        10 // 221 + n * 210 (index 52, end of cycle, wraps to index 5)
-    ];
+/// The 210-spoke wheel over `u128`.
+pub type PrimeWheel210 = Wheel210<u128>;
+
+const GAPS_210: [u8; 53] = [
+    2, 1, 2, 2, 4, // initial phase: 2, 3, 5, 7, 11 (index 0-4)
+    2, 4, 2, 4, 6, 2, 6, 4, 2, 4, 6, 6, 2, 6, 4, // 13..71 (index 5, start of cycle)
+    2, 6, 4, 6, 8, 4, 2, 4, 2, 4, 8, 6, 4, 6, 2, 4, // 73..143
+    6, 2, 6, 6, 4, 2, 4, 6, 2, 6, 4, 2, 4, 2, 10, 2, // 149..211
+    10 // 221 + n * 210 (index 52, end of cycle, wraps to index 5)
+];
+
+impl<T: PrimeInt> Wheel210<T> {
     pub fn new() -> Self {
-        Self::default()
+        Self { base: T::ZERO, index: 0 }
     }
     /// Create a wheel that will yield candidates >= `start`.
     /// The first call to `next()` returns the first candidate at or above `start`.
-    pub fn from(start: u128) -> Self {
-        if start <= 2 {
-            return Self::default();
+    pub fn from(start: T) -> Self {
+        if start <= T::from(2) {
+            return Self::new();
         }
         // For small starts, walk through the initial phase
-        if start <= 11 {
-            let mut base: u128 = 0;
-            for (i, &gap) in Self::GAPS.iter().enumerate().take(5) {
-                if base + gap >= start {
+        if start <= T::from(11) {
+            let mut base = T::ZERO;
+            for (i, &gap) in GAPS_210.iter().enumerate().take(5) {
+                if base + T::from(gap) >= start {
                     return Self { base, index: i };
                 }
-                base += gap;
+                base += T::from(gap);
             }
-            return Self { base: 7, index: 4 };
+            return Self { base: T::from(7), index: 4 };
         }
         // Jump into the correct 210-cycle.
         // Each cycle starts at base = 11 + k*210 and produces candidates
         // from base+2 up to base+210 (48 candidates spanning 210 values).
-        let k = start.saturating_sub(13) / 210;
-        let mut pos = 11 + k * 210;
-        for (i, &gap) in Self::GAPS[5..].iter().enumerate() {
-            if pos + gap >= start {
-                return Self { base: pos, index: i + 5 };
+        let k = if start > T::from(13) { (start - T::from(13)) / T::from(210) } else { T::ZERO };
+        let mut pos = T::from(11) + k * T::from(210);
+        for (i, &gap) in GAPS_210[5..].iter().enumerate() {
+            // A candidate beyond the type's range counts as past `start`;
+            // the iterator then ends on the first call to `next()`.
+            match pos.checked_add(T::from(gap)) {
+                Some(next) if next < start => pos = next,
+                _ => return Self { base: pos, index: i + 5 },
             }
-            pos += gap;
         }
         // All candidates in this cycle are below start; use next cycle
         Self { base: pos, index: 5 }
     }
 }
 
-impl Iterator for PrimeWheel210 {
-    type Item = u128;
+impl<T: PrimeInt> Wheel210<T> {
+    /// Whether the state is one the wheel can reach: the base is the
+    /// candidate that the gap at `index` steps on from.
+    #[cfg(feature = "serde")]
+    pub(crate) fn is_valid(&self) -> bool {
+        if self.index >= GAPS_210.len() {
+            return false;
+        }
+        // The position in the initial phase, or in the first 210-cycle
+        let offset = T::from(GAPS_210[..self.index].iter().sum::<u8>());
+        if self.index < 5 {
+            self.base == offset
+        } else {
+            self.base >= offset && (self.base - offset).is_multiple_of(T::from(210))
+        }
+    }
+}
+
+impl<T: PrimeInt> Iterator for Wheel210<T> {
+    type Item = T;
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
-        let gap = Self::GAPS.get(self.index)?;
-        self.base += gap;
+        let gap = GAPS_210.get(self.index)?;
+        self.base = self.base.checked_add(T::from(*gap))?;
         self.index += 1;
         if self.index == 53 {
             self.index = 5;
@@ -123,26 +153,26 @@ impl Iterator for PrimeWheel210 {
     }
 }
 
-impl PrimeWheel210 {
+impl<T: PrimeInt> Wheel210<T> {
     /// Navigate the wheel backward to yield the previous prime candidate.
     /// This works directly on the internal state without modifying the forward Iterator.
     /// It returns `None` when attempting to go before 2.
-    pub fn prev(&mut self) -> Option<u128> {
-        if self.base < 2 {
+    pub fn prev(&mut self) -> Option<T> {
+        if self.base < T::from(2) {
             return None;
         }
         let current = self.base;
         // Reverse step: If we are at the beginning of the recurring cycle
         // (index 5), wrap backwards to the end of the previous cycle.
-        if self.index == 5 && self.base > 11 {
+        if self.index == 5 && self.base > T::from(11) {
             self.index = 53;
         }
         if self.index > 0 {
             self.index -= 1;
-            let gap = Self::GAPS[self.index];
-            self.base = self.base.saturating_sub(gap);
+            let gap = T::from(GAPS_210[self.index]);
+            self.base = if self.base > gap { self.base - gap } else { T::ZERO };
         } else {
-            self.base = 0;
+            self.base = T::ZERO;
         }
         Some(current)
     }
@@ -152,86 +182,34 @@ impl PrimeWheel210 {
 /// Returns false for any number divisible by 2, 3, 5, or 7,
 /// eliminating ~77% of all composites with a single modulo + bit-test.
 #[inline(always)]
-pub(crate) fn is_prime_candidate(n: u128) -> bool {
-    if n < 11 {
-        return matches!(n, 2 | 3 | 5 | 7);
+pub(crate) fn is_prime_candidate<T: PrimeInt>(n: T) -> bool {
+    if n < T::from(11) {
+        return [2, 3, 5, 7].map(T::from).contains(&n);
     }
     const BITMAP: [u32; 7] = [
         0xa08a_2802, 0x2820_8a20, 0x0208_8288, 0x8202_28a2,
         0x20a0_8a08, 0x8828_2288, 0x0002_00a2,
     ];
-    let index = (n % 210) as usize;
+    let index = (n % T::from(210)).to_u128().unwrap_or_default() as usize;
     BITMAP[index / 32] & (1 << (index & 0x1F)) != 0
 }
 
-/// Modular exponentiation: (base^exp) mod modulus.
-#[inline]
-fn mod_pow(mut base: u128, mut exp: u128, modulus: u128) -> u128 {
-    if modulus == 1 { return 0; }
-    let mut result: u128 = 1;
-    base %= modulus;
-    while exp > 0 {
-        if exp & 1 == 1 {
-            result = mod_mul(result, base, modulus);
-        }
-        exp >>= 1;
-        if exp > 0 {
-            base = mod_mul(base, base, modulus);
-        }
-    }
-    result
-}
-
-/// Modular addition: (a + b) mod m, without overflow.
-/// Requires a < m and b < m.
-#[inline]
-fn add_mod(a: u128, b: u128, m: u128) -> u128 {
-    debug_assert!(a < m);
-    debug_assert!(b < m);
-    if a >= m - b {
-        a - (m - b)
-    } else {
-        a + b
-    }
-}
-
-/// Modular multiplication: (a * b) mod m, without overflow.
-/// Uses direct multiplication when the product fits in u128.
-/// For larger products, it uses Russian peasant multiplication.
-#[inline]
-fn mod_mul(a: u128, b: u128, m: u128) -> u128 {
-    debug_assert!(m > 0);
-    // For small moduli where a*b won't overflow u128, use direct multiplication
-    if a.leading_zeros() + b.leading_zeros() >= 128 {
-        return (a * b) % m;
-    }
-    let mut result: u128 = 0;
-    let mut a = a % m;
-    let mut b = b % m;
-    // Full-range safe fallback for very large moduli.
-    while b > 0 {
-        if b & 1 == 1 {
-            result = add_mod(result, a, m);
-        }
-        b >>= 1;
-        if b > 0 {
-            a = add_mod(a, a, m);
-        }
-    }
-    result
-}
-
-/// Test a single Miller-Rabin witness against n.
+/// Finish a single Miller-Rabin witness test, given x = a^d and the
+/// representations of 1 and −1 in the arithmetic used.
 /// Returns true if n passes the test for this witness (probably prime).
-fn miller_rabin_witness(n: u128, a: u128, d: u128, r: u32) -> bool {
-    debug_assert!(n >= 2);
-    let mut x = mod_pow(a, d, n);
-    if x == 1 || x == n - 1 {
+pub(crate) fn miller_rabin_witness<T: Eq + Copy>(
+    mut x: T,
+    one: T,
+    minus_one: T,
+    r: u32,
+    square: impl Fn(T) -> T,
+) -> bool {
+    if x == one || x == minus_one {
         return true;
     }
     for _ in 1..r {
-        x = mod_mul(x, x, n);
-        if x == n - 1 {
+        x = square(x);
+        if x == minus_one {
             return true;
         }
     }
@@ -242,6 +220,8 @@ fn miller_rabin_witness(n: u128, a: u128, d: u128, r: u32) -> bool {
 ///
 /// Uses witnesses {2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37} which are
 /// proven sufficient for all numbers below 3,317,044,064,679,887,385,961,981.
+/// Above 64 bits the arithmetic is done in Montgomery form, which keeps the
+/// cost per squaring close to that of the native products below.
 ///
 /// Reference: <https://en.wikipedia.org/wiki/Miller%E2%80%93Rabin_primality_test>
 pub(crate) fn miller_rabin(n: u128) -> bool {
@@ -250,13 +230,26 @@ pub(crate) fn miller_rabin(n: u128) -> bool {
     let n_minus_1 = n - 1;
     let r = n_minus_1.trailing_zeros();
     let d = n_minus_1 >> r;
-    WITNESSES.iter().all(|&a| a >= n || miller_rabin_witness(n, a, d, r))
+    if n > u64::MAX as u128
+        && let Some(mont) = Montgomery::new(n)
+    {
+        let (one, minus_one) = (mont.one(), mont.sub(0, mont.one()));
+        WITNESSES.iter().all(|&a| {
+            let x = mont.pow(mont.to_montgomery(a), d);
+            miller_rabin_witness(x, one, minus_one, r, |x| mont.mul(x, x))
+        })
+    } else {
+        WITNESSES.iter().all(|&a| {
+            a >= n || miller_rabin_witness(mod_pow(a, d, n), 1, n_minus_1, r, |x| mod_mul(x, x, n))
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use reikna::prime::{is_prime, next_prime};
-    use super::{PrimeWheel30, PrimeWheel210, add_mod, mod_mul};
+    use super::{PrimeWheel30, PrimeWheel210};
+    use crate::modular::{add_mod, mod_mul};
 
     fn mod_mul_reference(a: u128, b: u128, m: u128) -> u128 {
         let mut result = 0;
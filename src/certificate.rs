@@ -0,0 +1,230 @@
+//! Primality certificates that can be checked independently of the test
+//! that produced them.
+//!
+//! Pocklington's criterion: let n − 1 = F·R, where the prime factorization
+//! of F is known and F > √n. If for every prime q dividing F there is a
+//! witness a with a^(n−1) ≡ 1 (mod n) and gcd(a^((n−1)/q) − 1, n) = 1, then
+//! n is prime. The primes q need certificates of their own, which makes the
+//! certificate recursive; it ends at primes below the limit where the
+//! deterministic Miller-Rabin test is proven correct.
+//!
+//! Reference: <https://en.wikipedia.org/wiki/Pocklington_primality_test>
+use crate::modular::NON_RESIDUE_SEARCH;
+use crate::montgomery::Montgomery;
+use crate::{bpsw, u128_gcd, u128_is_prime, PrimeFactors, MR_DETERMINISTIC_LIMIT};
+
+/// A machine-checkable proof that a number is prime.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PrimalityCertificate {
+    /// A prime below the limit of the deterministic Miller-Rabin test
+    /// (about 3.3 × 10²⁴), which the verifier runs again.
+    Small(u128),
+    /// A prime proven with Pocklington's criterion on the factors of n − 1.
+    Pocklington { n: u128, factors: Vec<PocklingtonFactor> },
+}
+
+/// A prime power q^e dividing n − 1 in a [`PrimalityCertificate`], with the
+/// witness for q and a certificate that q is prime.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PocklingtonFactor {
+    pub exponent: u32,
+    pub witness: u128,
+    pub certificate: PrimalityCertificate,
+}
+
+impl PocklingtonFactor {
+    /// The prime q.
+    #[must_use]
+    pub fn prime(&self) -> u128 {
+        self.certificate.value()
+    }
+}
+
+impl PrimalityCertificate {
+    /// Prove that `n` is prime, factoring n − 1 with
+    /// [`PrimeFactors::factorize`] and recursing into its large prime
+    /// factors. Returns `None` when `n` is not a prime.
+    ///
+    /// Composites are turned away by the Baillie–PSW test before n − 1 is
+    /// factored. The witnesses are searched for among the bases below
+    /// [`NON_RESIDUE_SEARCH`], beyond which the search gives up with `None`;
+    /// a prime needs a quadratic non-residue as the witness for q = 2, and
+    /// under the generalised Riemann hypothesis there is one in range.
+    #[must_use]
+    pub fn new(n: u128) -> Option<Self> {
+        if n < MR_DETERMINISTIC_LIMIT {
+            return u128_is_prime(n).then_some(Self::Small(n));
+        }
+        if !bpsw::bpsw(n) {
+            return None;
+        }
+        let mont = Montgomery::new(n)?;
+        let n_minus_1 = n - 1;
+        let mut pending: Vec<(u128, u32)> = PrimeFactors::factorize(n_minus_1)
+            .factors()
+            .iter()
+            .map(|f| (f.integer, f.exponent))
+            .collect();
+        let mut witnesses = Vec::with_capacity(pending.len());
+        // For a prime n, a random a fails for q with probability 1/q, so
+        // this ends after a few bases. A composite that passed Baillie–PSW
+        // may never show itself here, so the search is bounded.
+        for a in 2..NON_RESIDUE_SEARCH {
+            let am = mont.to_montgomery(a);
+            if mont.pow(am, n_minus_1) != mont.one() {
+                return None;
+            }
+            let mut composite = false;
+            pending.retain(|&(q, e)| {
+                let g = witness_gcd(&mont, am, n_minus_1 / q);
+                if g == 1 {
+                    witnesses.push((q, e, a));
+                }
+                composite |= g != 1 && g != n;
+                g != 1
+            });
+            if composite {
+                return None;
+            }
+            if pending.is_empty() {
+                break;
+            }
+        }
+        if !pending.is_empty() {
+            return None;
+        }
+        let factors = witnesses
+            .into_iter()
+            .map(|(q, exponent, witness)| {
+                let certificate = Self::new(q)?;
+                Some(PocklingtonFactor { exponent, witness, certificate })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self::Pocklington { n, factors })
+    }
+    /// The number proven prime.
+    #[must_use]
+    pub fn value(&self) -> u128 {
+        match self {
+            Self::Small(n) | Self::Pocklington { n, .. } => *n,
+        }
+    }
+    /// Check the certificate, including those of the factors it relies on.
+    #[must_use]
+    pub fn verify(&self) -> bool {
+        match self {
+            Self::Small(n) => *n < MR_DETERMINISTIC_LIMIT && u128_is_prime(*n),
+            Self::Pocklington { n, factors } => verify_pocklington(*n, factors),
+        }
+    }
+}
+
+/// Check Pocklington's criterion for `n` with the given factors of n − 1.
+fn verify_pocklington(n: u128, factors: &[PocklingtonFactor]) -> bool {
+    let Some(mont) = Montgomery::new(n) else {
+        return false;
+    };
+    if n < 3 {
+        return false;
+    }
+    let n_minus_1 = n - 1;
+    let mut f: u128 = 1;
+    for factor in factors {
+        let q = factor.prime();
+        let Some(qe) = q.checked_pow(factor.exponent) else {
+            return false;
+        };
+        f = match f.checked_mul(qe) {
+            Some(f) if n_minus_1.is_multiple_of(f) => f,
+            _ => return false,
+        };
+        let a = factor.witness % n;
+        let am = mont.to_montgomery(a);
+        if factor.exponent == 0
+            || mont.pow(am, n_minus_1) != mont.one()
+            || witness_gcd(&mont, am, n_minus_1 / q) != 1
+            || !factor.certificate.verify()
+        {
+            return false;
+        }
+    }
+    // F > √n
+    f.checked_mul(f).is_none_or(|f2| f2 > n)
+}
+
+/// gcd(a^e − 1, n) for `a` in Montgomery form.
+fn witness_gcd(mont: &Montgomery, a: u128, e: u128) -> u128 {
+    let x = mont.from_montgomery(mont.pow(a, e));
+    let n = mont.modulus();
+    u128_gcd(if x == 0 { n - 1 } else { x - 1 }, n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PocklingtonFactor, PrimalityCertificate};
+
+    #[test]
+    fn test_certificate_small() {
+        assert_eq!(PrimalityCertificate::new(1000000007), Some(PrimalityCertificate::Small(1000000007)));
+        assert_eq!(PrimalityCertificate::new(1000000007 * 3), None);
+        assert!(!PrimalityCertificate::Small(1000000007 * 3).verify());
+        assert!(!PrimalityCertificate::Small(1).verify());
+    }
+
+    #[test]
+    fn test_certificate_large_primes() {
+        let primes: [u128; 4] = [
+            (1 << 89) - 1,
+            (1 << 107) - 1,
+            (1 << 127) - 1,
+            85070591730234615865843651857942052727,
+        ];
+        for p in primes {
+            let cert = PrimalityCertificate::new(p).unwrap();
+            assert_eq!(cert.value(), p);
+            assert!(cert.verify(), "certificate for {p}");
+        }
+        // A strong pseudoprime to the first twelve prime bases is caught
+        assert_eq!(PrimalityCertificate::new(3317044064679887385961981), None);
+        assert_eq!(PrimalityCertificate::new(18446744073709551557 * 18446744073709551533), None);
+        // A Carmichael number, for which every coprime base passes Fermat
+        assert_eq!(PrimalityCertificate::new(84001441 * 168002881 * 252004321), None);
+    }
+
+    #[test]
+    fn test_certificate_slow_case() {
+        // The slowest of 300 random 128-bit primes, at about 150 ms in a
+        // release build: n - 1 has two large prime factors
+        let p: u128 = 189488405394347758358649000054806754547;
+        assert!(PrimalityCertificate::new(p).unwrap().verify());
+    }
+
+    #[test]
+    fn test_certificate_rejects_forgeries() {
+        let p: u128 = (1 << 89) - 1;
+        let cert = PrimalityCertificate::new(p).unwrap();
+        let PrimalityCertificate::Pocklington { factors, .. } = &cert else {
+            panic!("expected a Pocklington certificate");
+        };
+        // The same factors do not prove a different number
+        let forged = PrimalityCertificate::Pocklington { n: p + 2, factors: factors.clone() };
+        assert!(!forged.verify());
+        // Dropping factors leaves F below √n
+        let forged = PrimalityCertificate::Pocklington { n: p, factors: factors[..1].to_vec() };
+        assert!(!forged.verify());
+        // A witness of 1 proves nothing
+        let mut bad = factors.clone();
+        bad[0] = PocklingtonFactor { witness: 1, ..bad[0].clone() };
+        assert!(!PrimalityCertificate::Pocklington { n: p, factors: bad }.verify());
+        // A composite with the factors of its n - 1 and no valid witnesses
+        let n: u128 = 3317044064679887385961981;
+        let factors = crate::PrimeFactors::factorize(n - 1).factors().iter()
+            .map(|f| PocklingtonFactor {
+                exponent: f.exponent,
+                witness: 2,
+                certificate: PrimalityCertificate::new(f.integer).unwrap(),
+            })
+            .collect();
+        assert!(!PrimalityCertificate::Pocklington { n, factors }.verify());
+    }
+}
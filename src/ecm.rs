@@ -0,0 +1,291 @@
+//! Lenstra's elliptic curve method (ECM) on Montgomery curves.
+//!
+//! Each curve B·y² = x³ + A·x² + x (mod n) has a group order modulo every
+//! prime factor p of n that varies from curve to curve. Whenever that order
+//! is smooth with respect to the bounds, multiplying a point by all the
+//! small prime powers sends it to the point at infinity modulo p, and a gcd
+//! with the Z coordinate reveals p. The running time depends on the size of
+//! the factor found, not on the size of n, which suits factors of 30 to 50
+//! bits hidden in numbers near `u128::MAX`.
+//!
+//! Points are kept in projective (X : Z) form, so no modular inversions are
+//! needed outside of curve setup, and all coordinates are kept in
+//! Montgomery form (see [`crate::montgomery`]). Stage 1 uses the Montgomery
+//! ladder, and stage 2 uses a baby-step giant-step continuation with a 2310
+//! wheel.
+//!
+//! Reference: <https://en.wikipedia.org/wiki/Lenstra_elliptic-curve_factorization>
+use crate::modular::{self, add_mod, mod_mul, sub_mod};
+use crate::montgomery::Montgomery;
+use crate::sieve::SegmentedSieve;
+use crate::{u128_gcd, u128_is_prime};
+
+/// The giant step of stage 2. Every prime above 11 is m·D ± j for some j
+/// coprime to D below D/2, so only φ(D)/2 = 240 baby steps are needed.
+const STAGE2_WHEEL: u64 = 2310;
+
+/// Bounds and effort for the elliptic curve method.
+///
+/// Stage 1 multiplies each curve point by every prime power up to `b1`,
+/// and stage 2 allows for one additional prime in `(b1, b2]`. The default
+/// targets factors of around 35 bits; raise `b1` (keeping `b2` near
+/// 100·`b1`) and `curves` for larger factors.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EcmParams {
+    pub b1: u64,
+    pub b2: u64,
+    pub curves: u32,
+}
+
+impl EcmParams {
+    #[must_use]
+    pub fn new(b1: u64, b2: u64, curves: u32) -> Self {
+        Self { b1, b2, curves }
+    }
+}
+
+impl Default for EcmParams {
+    fn default() -> Self {
+        Self { b1: 2_000, b2: 200_000, curves: 64 }
+    }
+}
+
+/// Find a non-trivial factor of `n` with the elliptic curve method.
+///
+/// Returns `None` when `n` is 0, 1, a prime, or when none of the curves
+/// found a factor within the given bounds. The returned factor is not
+/// necessarily prime.
+#[must_use]
+pub fn ecm(n: u128, params: &EcmParams) -> Option<u128> {
+    if n < 4 || u128_is_prime(n) {
+        return None;
+    }
+    find_factor(n, params)
+}
+
+/// Find a non-trivial factor of the composite `n`, trying each curve in turn.
+pub(crate) fn find_factor(n: u128, params: &EcmParams) -> Option<u128> {
+    debug_assert!(n >= 4);
+    if n.is_multiple_of(2) {
+        return Some(2);
+    }
+    let plan = Plan::new(params.b1, params.b2);
+    (0..params.curves).find_map(|i| {
+        let sigma = 6 + u128::from(i);
+        match Curve::suyama(n, sigma) {
+            Ok((curve, point)) => curve.run(point, &plan),
+            Err(g) => (g > 1 && g < n).then_some(g),
+        }
+    })
+}
+
+/// A projective point (X : Z) on a Montgomery curve; the Y coordinate is
+/// never needed.
+#[derive(Clone, Copy, Debug)]
+struct Point {
+    x: u128,
+    z: u128,
+}
+
+/// A Montgomery curve modulo n, described by a24 = (A + 2) / 4.
+struct Curve {
+    n: u128,
+    mont: Montgomery,
+    a24: u128,
+}
+
+impl Curve {
+    /// Construct a curve and starting point from Suyama's parametrization,
+    /// which guarantees a group order divisible by 12. If the setup needs
+    /// to invert a value that shares a factor with n, that gcd is returned
+    /// as the error instead. The setup runs on ordinary residues, and the
+    /// result is converted to Montgomery form.
+    fn suyama(n: u128, sigma: u128) -> Result<(Self, Point), u128> {
+        let m = |a, b| mod_mul(a, b, n);
+        let sigma = sigma % n;
+        let u = sub_mod(m(sigma, sigma), 5 % n, n);
+        let v = m(4 % n, sigma);
+        let u3 = m(m(u, u), u);
+        let v3 = m(m(v, v), v);
+        let vmu = sub_mod(v, u, n);
+        let vmu3 = m(m(vmu, vmu), vmu);
+        let three_u_plus_v = add_mod(m(3 % n, u), v, n);
+        // a24 = (v - u)³ (3u + v) / (16 u³ v)
+        let num = m(vmu3, three_u_plus_v);
+        let den = m(m(16 % n, u3), v);
+        let inv = mod_inverse(den, n)?;
+        let mont = Montgomery::new(n).ok_or(2_u128)?;
+        let curve = Curve { n, mont, a24: mont.to_montgomery(m(num, inv)) };
+        let point = Point { x: mont.to_montgomery(u3), z: mont.to_montgomery(v3) };
+        Ok((curve, point))
+    }
+
+    #[inline]
+    fn mul(&self, a: u128, b: u128) -> u128 {
+        self.mont.mul(a, b)
+    }
+
+    /// Point doubling: 2P.
+    fn double(&self, p: Point) -> Point {
+        let n = self.n;
+        let s = add_mod(p.x, p.z, n);
+        let d = sub_mod(p.x, p.z, n);
+        let s2 = self.mul(s, s);
+        let d2 = self.mul(d, d);
+        let t = sub_mod(s2, d2, n);
+        Point {
+            x: self.mul(s2, d2),
+            z: self.mul(t, add_mod(d2, self.mul(self.a24, t), n)),
+        }
+    }
+
+    /// Differential addition: P + Q, given the difference P - Q.
+    fn add(&self, p: Point, q: Point, diff: Point) -> Point {
+        let n = self.n;
+        let u = self.mul(sub_mod(p.x, p.z, n), add_mod(q.x, q.z, n));
+        let v = self.mul(add_mod(p.x, p.z, n), sub_mod(q.x, q.z, n));
+        let sum = add_mod(u, v, n);
+        let dif = sub_mod(u, v, n);
+        Point {
+            x: self.mul(diff.z, self.mul(sum, sum)),
+            z: self.mul(diff.x, self.mul(dif, dif)),
+        }
+    }
+
+    /// Scalar multiplication kP with the Montgomery ladder (k >= 1).
+    fn ladder(&self, k: u64, p: Point) -> Point {
+        debug_assert!(k >= 1);
+        let mut r0 = p;
+        let mut r1 = self.double(p);
+        for bit in (0..63 - k.leading_zeros()).rev() {
+            if (k >> bit) & 1 == 1 {
+                r0 = self.add(r1, r0, p);
+                r1 = self.double(r1);
+            } else {
+                r1 = self.add(r0, r1, p);
+                r0 = self.double(r0);
+            }
+        }
+        r0
+    }
+
+    /// Run both stages on this curve, returning a proper factor of n if found.
+    fn run(&self, mut q: Point, plan: &Plan) -> Option<u128> {
+        let n = self.n;
+        // Stage 1: multiply by the largest power of every prime up to B1.
+        for &pk in &plan.prime_powers {
+            q = self.ladder(pk, q);
+        }
+        let g = u128_gcd(q.z, n);
+        if g > 1 {
+            return (g < n).then_some(g);
+        }
+        if plan.pairs.is_empty() {
+            return None;
+        }
+        // Stage 2, baby steps: jQ for every odd j below D/2.
+        let len = (STAGE2_WHEEL as usize / 2).div_ceil(2);
+        let mut baby = vec![q; len];
+        let q2 = self.double(q);
+        baby[1] = self.add(q2, q, q);
+        for i in 2..len {
+            baby[i] = self.add(baby[i - 1], q2, baby[i - 2]);
+        }
+        // Giant steps: G_m = mDQ, advanced by differential addition.
+        let step = self.ladder(STAGE2_WHEEL, q);
+        let mut m = plan.pairs[0].0.max(1);
+        let mut cur = self.ladder(m * STAGE2_WHEEL, q);
+        let mut next = self.ladder((m + 1) * STAGE2_WHEEL, q);
+        let mut acc = self.mont.one();
+        for &(pm, j) in &plan.pairs {
+            let b = baby[j as usize / 2];
+            // (mD ± j)Q is the point at infinity modulo p exactly when
+            // G_m and jQ share an x coordinate, i.e. X_G·Z_j = X_j·Z_G.
+            let cross = if pm == 0 {
+                b.z
+            } else {
+                while m < pm {
+                    let after = self.add(next, step, cur);
+                    cur = next;
+                    next = after;
+                    m += 1;
+                }
+                sub_mod(self.mul(cur.x, b.z), self.mul(b.x, cur.z), n)
+            };
+            acc = self.mul(acc, cross);
+        }
+        let g = u128_gcd(acc, n);
+        (g > 1 && g < n).then_some(g)
+    }
+}
+
+/// The scalars for both stages, computed once and shared by every curve.
+struct Plan {
+    /// The largest power of each prime up to B1.
+    prime_powers: Vec<u64>,
+    /// The (m, j) pairs with m·D ± j prime in (B1, B2].
+    pairs: Vec<(u64, u64)>,
+}
+
+impl Plan {
+    fn new(b1: u64, b2: u64) -> Self {
+        let mut prime_powers = Vec::new();
+        let mut pairs = Vec::new();
+        let d = STAGE2_WHEEL;
+        for p in SegmentedSieve::new(2..b1.max(b2).saturating_add(1)) {
+            if p <= b1 {
+                let mut pk = p;
+                while pk <= b1 / p {
+                    pk *= p;
+                }
+                prime_powers.push(pk);
+            } else {
+                let m = (p + d / 2) / d;
+                pairs.push((m, p.abs_diff(m * d)));
+            }
+        }
+        Self { prime_powers, pairs }
+    }
+}
+
+/// Modular inverse of a modulo m via the extended Euclidean algorithm.
+/// Returns the gcd as the error when a is not invertible.
+fn mod_inverse(a: u128, m: u128) -> Result<u128, u128> {
+    modular::mod_inverse(a, m).ok_or_else(|| u128_gcd(a, m))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ecm, mod_inverse, EcmParams};
+    use crate::modular::mod_mul;
+
+    #[test]
+    fn test_mod_inverse() {
+        let m: u128 = 1000000007;
+        for a in [1, 2, 3, 12345, m - 1] {
+            let inv = mod_inverse(a, m).unwrap();
+            assert_eq!(mod_mul(a, inv, m), 1);
+        }
+        assert_eq!(mod_inverse(6, 15), Err(3));
+        assert_eq!(mod_inverse(0, 15), Err(15));
+    }
+
+    #[test]
+    fn test_ecm_small_factors() {
+        // Tiny factors break the curve setup, and surface through its gcds
+        let params = EcmParams::default();
+        let q: u128 = 2305843009213693951;
+        for p in [2, 3, 5, 7, 13] {
+            assert_eq!(ecm(p * q, &params), Some(p), "ecm({p} * {q})");
+        }
+    }
+
+    #[test]
+    fn test_ecm_finds_medium_factor() {
+        let params = EcmParams::new(2_000, 200_000, 40);
+        // A 30-bit prime times a 61-bit prime
+        let (p, q): (u128, u128) = (1000000007, 2305843009213693951);
+        let d = ecm(p * q, &params).unwrap();
+        assert!(d == p || d == q, "{d} is not a factor of {p} * {q}");
+    }
+}
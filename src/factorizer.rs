@@ -0,0 +1,324 @@
+//! A configurable factorization pipeline.
+//!
+//! [`PrimeFactors::factorize`] always runs the same sequence of methods. A
+//! [`Factorizer`] lets the caller choose the trial division bound and the
+//! methods used to split whatever cofactor remains, in order, including
+//! their own through the [`FactorMethod`] trait. The small primes used for
+//! trial division are computed once when the factorizer is built and reused
+//! by every call.
+use crate::ecm::EcmParams;
+use crate::sieve::primes_up_to;
+use crate::{
+    ecm, fermat, pollard, siqs, squfof, u128_is_prime_with, williams, PrimalityTest, PrimeFactors,
+    FERMAT_ITERATIONS, MR_TRIAL_DIVISION_CROSSOVER, PM1_B1, PM1_B2, RHO_ITERATION_LIMIT,
+    TRIAL_DIVISION_LIMIT,
+};
+
+/// The largest trial division bound a [`Factorizer`] accepts. The primes
+/// up to it, about 14.6 million, take 117 MB; larger bounds are clamped.
+pub const MAX_TRIAL_DIVISION_LIMIT: u128 = 1 << 28;
+
+/// A method that tries to split a composite into two proper factors.
+///
+/// The pipeline only passes composites without prime factors up to the
+/// trial division bound. A method returns `None` when it gives up; any
+/// value that is not a proper divisor of `n` is treated the same way.
+/// Closures of type `Fn(u128) -> Option<u128>` implement this trait.
+pub trait FactorMethod: Send + Sync {
+    fn find_factor(&self, n: u128) -> Option<u128>;
+}
+
+impl<F: Fn(u128) -> Option<u128> + Send + Sync> FactorMethod for F {
+    fn find_factor(&self, n: u128) -> Option<u128> {
+        self(n)
+    }
+}
+
+/// Fermat's method with a step budget (see [`fermat::fermat`]).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Fermat {
+    pub max_iterations: u128,
+}
+
+impl FactorMethod for Fermat {
+    fn find_factor(&self, n: u128) -> Option<u128> {
+        fermat::find_factor(n, self.max_iterations)
+    }
+}
+
+/// Lehman's method, for composites below [`fermat::LEHMAN_LIMIT`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Lehman;
+
+impl FactorMethod for Lehman {
+    fn find_factor(&self, n: u128) -> Option<u128> {
+        (n < fermat::LEHMAN_LIMIT).then(|| fermat::lehman_find_factor(n)).flatten()
+    }
+}
+
+/// SQUFOF, for composites below [`squfof::SQUFOF_LIMIT`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Squfof;
+
+impl FactorMethod for Squfof {
+    fn find_factor(&self, n: u128) -> Option<u128> {
+        (n < squfof::SQUFOF_LIMIT).then(|| squfof::find_factor(n)).flatten()
+    }
+}
+
+/// Pollard's rho, giving up after about `max_iterations` steps on each of
+/// a few polynomials.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PollardRho {
+    pub max_iterations: u128,
+}
+
+impl FactorMethod for PollardRho {
+    fn find_factor(&self, n: u128) -> Option<u128> {
+        pollard::find_factor_within(n, self.max_iterations)
+    }
+}
+
+/// Pollard's p−1 with smoothness bounds (see [`pollard::pollard_pm1`]).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PollardPm1 {
+    pub b1: u64,
+    pub b2: u64,
+}
+
+impl FactorMethod for PollardPm1 {
+    fn find_factor(&self, n: u128) -> Option<u128> {
+        pollard::find_factor_pm1(n, self.b1, self.b2)
+    }
+}
+
+/// Williams' p+1 with smoothness bounds (see [`williams::williams_pp1`]).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WilliamsPp1 {
+    pub b1: u64,
+    pub b2: u64,
+}
+
+impl FactorMethod for WilliamsPp1 {
+    fn find_factor(&self, n: u128) -> Option<u128> {
+        williams::find_factor(n, self.b1, self.b2)
+    }
+}
+
+/// The elliptic curve method (see [`ecm::ecm`]).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Ecm {
+    pub params: EcmParams,
+}
+
+impl FactorMethod for Ecm {
+    fn find_factor(&self, n: u128) -> Option<u128> {
+        ecm::find_factor(n, &self.params)
+    }
+}
+
+/// The self-initialising quadratic sieve, for composites of at least
+/// [`siqs::SIQS_MIN`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Siqs;
+
+impl FactorMethod for Siqs {
+    fn find_factor(&self, n: u128) -> Option<u128> {
+        (n >= siqs::SIQS_MIN).then(|| siqs::find_factor(n)).flatten()
+    }
+}
+
+/// Builds a [`Factorizer`]; see [`Factorizer::builder`].
+pub struct FactorizerBuilder {
+    trial_division_limit: u128,
+    primality_test: PrimalityTest,
+    methods: Vec<Box<dyn FactorMethod>>,
+}
+
+impl FactorizerBuilder {
+    /// Trial divide by every prime up to `limit` before the methods run.
+    /// The limit is clamped to [`MAX_TRIAL_DIVISION_LIMIT`].
+    #[must_use]
+    pub fn trial_division_limit(mut self, limit: u128) -> Self {
+        self.trial_division_limit = limit.min(MAX_TRIAL_DIVISION_LIMIT);
+        self
+    }
+    /// Decide primality of the input and of every cofactor with `test`.
+    /// [`PrimalityTest::Bpsw`] keeps the factorization of numbers with
+    /// large prime factors fast, at the level of assurance of that test.
+    #[must_use]
+    pub fn primality_test(mut self, test: PrimalityTest) -> Self {
+        self.primality_test = test;
+        self
+    }
+    /// Append a method to the pipeline. Methods are tried in the order
+    /// they were added, until one of them splits the cofactor.
+    #[must_use]
+    pub fn method(mut self, method: impl FactorMethod + 'static) -> Self {
+        self.methods.push(Box::new(method));
+        self
+    }
+    /// Precompute the trial division primes and build the factorizer.
+    #[must_use]
+    pub fn build(self) -> Factorizer {
+        let limit = self.trial_division_limit;
+        Factorizer {
+            small_primes: primes_up_to(limit as u64),
+            trial_division_limit: limit,
+            primality_test: self.primality_test,
+            methods: self.methods,
+        }
+    }
+}
+
+/// A reusable factorization pipeline: trial division up to a bound,
+/// followed by a list of methods for the remaining cofactor.
+///
+/// If every method gives up on a cofactor, it is split with Pollard's rho
+/// without an iteration limit, so the result is always complete.
+pub struct Factorizer {
+    small_primes: Vec<u64>,
+    trial_division_limit: u128,
+    primality_test: PrimalityTest,
+    methods: Vec<Box<dyn FactorMethod>>,
+}
+
+impl Factorizer {
+    /// Start building a factorizer with the default trial division bound,
+    /// proven primality and no methods.
+    #[must_use]
+    pub fn builder() -> FactorizerBuilder {
+        FactorizerBuilder {
+            trial_division_limit: TRIAL_DIVISION_LIMIT,
+            primality_test: PrimalityTest::Proven,
+            methods: Vec::new(),
+        }
+    }
+    /// A factorizer with the methods [`PrimeFactors::factorize`] uses for
+    /// large numbers, in the same order. Unlike `factorize`, which hands a
+    /// cofactor below [`squfof::SQUFOF_LIMIT`] that SQUFOF cannot split
+    /// straight to an unlimited rho, the pipeline tries p−1, a short rho
+    /// and SIQS on it first.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::builder()
+            .method(Fermat { max_iterations: FERMAT_ITERATIONS })
+            .method(Squfof)
+            .method(PollardPm1 { b1: PM1_B1, b2: PM1_B2 })
+            .method(PollardRho { max_iterations: RHO_ITERATION_LIMIT })
+            .method(Siqs)
+            .build()
+    }
+    /// Compute the prime factorization of n with this pipeline.
+    #[must_use]
+    pub fn factorize(&self, n: u128) -> PrimeFactors {
+        let mut pf = PrimeFactors::new();
+        if n < 2 { return pf; }
+        if self.is_prime(n) {
+            pf.add(n, 1);
+            return pf;
+        }
+        let mut x = n;
+        for p in self.small_primes.iter().map(|&p| u128::from(p)) {
+            if p * p > x { break; }
+            let mut c = 0;
+            while x.is_multiple_of(p) {
+                x /= p;
+                c += 1;
+            }
+            if c > 0 {
+                pf.add(p, c);
+                if x > MR_TRIAL_DIVISION_CROSSOVER && self.is_prime(x) {
+                    pf.add(x, 1);
+                    return pf;
+                }
+            }
+        }
+        if x > 1 {
+            let mut primes = Vec::new();
+            self.split(x, &mut primes);
+            primes.sort_unstable();
+            pf.add_sorted(&primes);
+        }
+        pf
+    }
+    fn is_prime(&self, n: u128) -> bool {
+        u128_is_prime_with(n, self.primality_test)
+    }
+    /// Recursively split a cofactor free of small factors into primes.
+    fn split(&self, n: u128, primes: &mut Vec<u128>) {
+        if n == 1 { return; }
+        let limit = self.trial_division_limit;
+        if n <= limit.saturating_mul(limit) || self.is_prime(n) {
+            primes.push(n);
+            return;
+        }
+        let d = self.methods.iter()
+            .filter_map(|m| m.find_factor(n))
+            .find(|&d| d > 1 && d < n && n.is_multiple_of(d))
+            .unwrap_or_else(|| pollard::find_factor(n));
+        self.split(d, primes);
+        self.split(n / d, primes);
+    }
+}
+
+impl Default for Factorizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Ecm, FactorMethod, Factorizer, Fermat, Lehman, PollardRho, Siqs, Squfof, WilliamsPp1,
+        MAX_TRIAL_DIVISION_LIMIT,
+    };
+    use crate::PrimeFactors;
+
+    #[test]
+    fn test_default_factorizer_matches_factorize() {
+        let factorizer = Factorizer::default();
+        for n in [0, 1, 2, 12, 600851475143, 1000003 * 1000033, u64::MAX as u128, u128::MAX] {
+            assert_eq!(factorizer.factorize(n), PrimeFactors::factorize(n), "factorize({n})");
+        }
+    }
+
+    #[test]
+    fn test_custom_pipeline() {
+        let factorizer = Factorizer::builder()
+            .trial_division_limit(100)
+            .method(Fermat { max_iterations: 16 })
+            .method(Lehman)
+            .method(WilliamsPp1 { b1: 1_000, b2: 50_000 })
+            .method(Ecm::default())
+            .build();
+        let n: u128 = 2 * 2 * 97 * 101 * 65521 * 65537;
+        assert_eq!(factorizer.factorize(n).to_string(), "2^2 * 97 * 101 * 65521 * 65537");
+        let (p, q): (u128, u128) = (1000000007, 2305843009213693951);
+        assert_eq!(factorizer.factorize(p * q).to_vec(), vec![p, q]);
+        // The wrappers decline composites outside the range of their method
+        let big: u128 = 1000000007 * 2305843009213693951;
+        assert_eq!((Lehman.find_factor(big), Squfof.find_factor(big)), (None, None));
+        assert_eq!(Siqs.find_factor(1000003 * 1000033), None);
+        assert_eq!(Squfof.find_factor(1000003 * 1000033), Some(1000003));
+        // Bounds past the maximum are clamped instead of collecting the primes
+        let builder = Factorizer::builder().trial_division_limit(1 << 40);
+        assert_eq!(builder.trial_division_limit, MAX_TRIAL_DIVISION_LIMIT);
+    }
+
+    #[test]
+    fn test_user_supplied_methods() {
+        // A method that always gives up, and one that returns garbage: the
+        // pipeline falls back to an unlimited rho and still completes.
+        let factorizer = Factorizer::builder()
+            .trial_division_limit(0)
+            .method(|_| None)
+            .method(|n| Some(n + 1))
+            .method(PollardRho { max_iterations: 1 })
+            .build();
+        let n: u128 = 1000003 * 1000033 * 4294967291;
+        assert_eq!(factorizer.factorize(n).to_vec(), vec![1000003, 1000033, 4294967291]);
+        assert_eq!(factorizer.factorize(64).to_string(), "2^6");
+    }
+}
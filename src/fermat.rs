@@ -0,0 +1,179 @@
+//! Difference-of-squares factoring: Fermat's method and Lehman's method.
+//!
+//! Fermat's method writes an odd n as a² − b² = (a − b)(a + b), walking a
+//! upwards from ⌈√n⌉ until a² − n is a perfect square. The number of steps
+//! grows with the square of the distance between the two factors, so a
+//! product of two nearly equal primes, the worst case for trial division,
+//! is split in a handful of steps.
+//!
+//! Lehman's method runs the same search on 4kn for every multiplier k up to
+//! ∛n, after trial division up to ∛n. It is guaranteed to find a factor of
+//! any composite in O(∛n) steps, which makes it practical below 64 bits.
+//!
+//! Reference: <https://en.wikipedia.org/wiki/Fermat%27s_factorization_method>
+//! Reference: <https://en.wikipedia.org/wiki/Lehman%27s_factorization_method>
+use crate::candidates::PrimeWheel210;
+use crate::{u128_gcd, u128_is_prime};
+
+/// The exclusive upper bound for [`lehman`]. The running time grows as ∛n,
+/// so larger inputs are better served by the other methods.
+pub const LEHMAN_LIMIT: u128 = 1 << 64;
+
+/// A bitmask of the squares modulo 64, used to reject most non-squares
+/// without computing a square root.
+const SQUARES_MOD_64: u64 = 0x0202_0212_0203_0213;
+
+/// Find a non-trivial factor of `n` with Fermat's method, giving up after
+/// `max_iterations` steps.
+///
+/// Returns `None` when `n` is 0, 1 or a prime, or when the two factors
+/// closest to √n are too far apart to be found within the budget. The
+/// returned factor is the largest divisor of `n` not above √n, found for
+/// odd `n`; for even `n`, 2 is returned.
+#[must_use]
+pub fn fermat(n: u128, max_iterations: u128) -> Option<u128> {
+    if n < 4 || u128_is_prime(n) {
+        return None;
+    }
+    find_factor(n, max_iterations)
+}
+
+/// Run Fermat's method on the composite `n`.
+pub(crate) fn find_factor(n: u128, max_iterations: u128) -> Option<u128> {
+    debug_assert!(n >= 4);
+    if n.is_multiple_of(2) {
+        return Some(2);
+    }
+    let mut a = n.isqrt();
+    if a * a == n {
+        return Some(a);
+    }
+    a += 1;
+    // r = a² − n, kept up to date as a increases. The square itself can
+    // exceed u128 when n is close to u128::MAX, but r always fits.
+    let mut r = a.wrapping_mul(a).wrapping_sub(n);
+    for _ in 0..max_iterations {
+        if let Some(b) = square_root(r) {
+            return (a - b > 1).then_some(a - b);
+        }
+        r = r.checked_add(2 * a + 1)?;
+        a += 1;
+    }
+    None
+}
+
+/// Find a non-trivial factor of `n` with Lehman's method.
+///
+/// Returns `None` when `n` is 0, 1 or a prime, and when `n` is not below
+/// [`LEHMAN_LIMIT`]. Every other composite is split. The returned factor is
+/// not necessarily prime.
+#[must_use]
+pub fn lehman(n: u128) -> Option<u128> {
+    if !(4..LEHMAN_LIMIT).contains(&n) || u128_is_prime(n) {
+        return None;
+    }
+    lehman_find_factor(n)
+}
+
+/// Run Lehman's method on the composite `n` below [`LEHMAN_LIMIT`].
+pub(crate) fn lehman_find_factor(n: u128) -> Option<u128> {
+    debug_assert!((4..LEHMAN_LIMIT).contains(&n));
+    if n.is_multiple_of(2) {
+        return Some(2);
+    }
+    let c = cube_root(n);
+    if let Some(f) = PrimeWheel210::new()
+        .take_while(|&f| f <= c)
+        .find(|&f| n.is_multiple_of(f))
+    {
+        return Some(f);
+    }
+    // Every factor is now above ∛n, so n has exactly two prime factors.
+    let sixth_root = (n as f64).powf(1.0 / 6.0);
+    for k in 1..=c {
+        let kn4 = 4 * k * n;
+        let mut a = kn4.isqrt();
+        if a * a < kn4 {
+            a += 1;
+        }
+        // a runs up to √(4kn) + n^(1/6) / (4√k); one extra step covers
+        // the rounding of the floating-point bound.
+        let steps = (sixth_root / (4.0 * (k as f64).sqrt())) as u128 + 1;
+        for a in a..=a + steps {
+            if let Some(b) = square_root(a * a - kn4) {
+                let g = u128_gcd(a + b, n);
+                if g > 1 && g < n {
+                    return Some(g);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Return √r when r is a perfect square.
+fn square_root(r: u128) -> Option<u128> {
+    if (SQUARES_MOD_64 >> (r % 64)) & 1 == 0 {
+        return None;
+    }
+    let s = r.isqrt();
+    (s * s == r).then_some(s)
+}
+
+/// The integer cube root ⌊∛n⌋.
+fn cube_root(n: u128) -> u128 {
+    let mut c = (n as f64).cbrt() as u128;
+    while c * c * c > n {
+        c -= 1;
+    }
+    while (c + 1) * (c + 1) * (c + 1) <= n {
+        c += 1;
+    }
+    c
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cube_root, fermat, lehman, LEHMAN_LIMIT};
+
+    #[test]
+    fn test_fermat_close_factors() {
+        // Two 64-bit primes 24 apart
+        let (p, q): (u128, u128) = (18446744073709551533, 18446744073709551557);
+        assert_eq!(fermat(p * q, 1), Some(p));
+        assert_eq!(fermat(1000003 * 1000033, 8), Some(1000003));
+        assert_eq!(fermat(1000003 * 1000003, 1), Some(1000003));
+        // Factors too far apart for the budget
+        assert_eq!(fermat(1000003 * 4294967291, 16), None);
+        // Perfect squares and even numbers are split before the first step
+        assert_eq!(fermat(4294967291 * 4294967291, 0), Some(4294967291));
+        assert_eq!(fermat(2 * 4294967291, 0), Some(2));
+    }
+
+    #[test]
+    fn test_lehman() {
+        let cases: [(u128, u128); 4] = [
+            (3, 5),
+            (65521, 65537),
+            (1000003, 4294967291),
+            (4294967279, 4294967291),
+        ];
+        for (p, q) in cases {
+            let d = lehman(p * q).unwrap();
+            assert!(d == p || d == q, "{d} is not a factor of {p} * {q}");
+        }
+        assert_eq!(lehman(LEHMAN_LIMIT + 1), None);
+        for n in (4..10_000u128).filter(|&n| !crate::u128_is_prime(n)) {
+            let d = lehman(n).unwrap();
+            assert!(d > 1 && d < n && n.is_multiple_of(d), "lehman({n}) = {d}");
+        }
+    }
+
+    #[test]
+    fn test_cube_root() {
+        for n in [0u128, 1, 7, 8, 9, 26, 27, 28, 999_999, 1_000_000, u64::MAX as u128] {
+            let c = cube_root(n);
+            assert!(c * c * c <= n && (c + 1) * (c + 1) * (c + 1) > n, "cube_root({n}) = {c}");
+        }
+    }
+}
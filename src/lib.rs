@@ -1,11 +1,34 @@
 //! Module for factorizing integers
 #![deny(unsafe_code)]
+pub mod bpsw;
 pub mod candidates;
+pub mod certificate;
+pub mod ecm;
+pub mod factorizer;
+pub mod fermat;
+pub mod lmo;
+pub mod modular;
+pub mod montgomery;
+pub mod pollard;
+pub mod primeint;
+#[cfg(feature = "serde")]
+mod serialization;
+pub mod sieve;
+pub mod siqs;
+pub mod squfof;
+pub mod u256;
+pub mod williams;
 
-use std::cmp::{min, Ordering};
+use std::cmp::{max, min, Ordering};
 use std::fmt;
-use candidates::PrimeWheel210 as PrimeWheel;
+use std::str::FromStr;
+use candidates::Wheel210;
 use candidates::{is_prime_candidate, miller_rabin};
+use certificate::PrimalityCertificate;
+pub use lmo::{nth_prime, prime_pi, PRIME_PI_LIMIT};
+pub use modular::u128_xgcd;
+pub use primeint::{Factorable, PrimeInt};
+pub use u256::U256;
 
 /// The threshold where Miller-Rabin primality checking becomes faster than
 /// naive trial division. Below this limit, testing wheel candidates up to
@@ -17,21 +40,64 @@ const MR_TRIAL_DIVISION_CROSSOVER: u128 = 10_000_000;
 /// Above this limit, MR operates probabilistically and needs fallback verification.
 const MR_DETERMINISTIC_LIMIT: u128 = 3_317_044_064_679_887_385_961_981;
 
+/// The bound for wheel trial division in the large-number path. Any
+/// cofactor left without a factor below this bound is split with
+/// SQUFOF when it fits below [`squfof::SQUFOF_LIMIT`], and otherwise with
+/// Pollard's p−1 and rho, backed by the self-initialising quadratic sieve.
+const TRIAL_DIVISION_LIMIT: u128 = 1 << 12;
+
+/// The number of Pollard's rho iterations spent on a cofactor above the
+/// SQUFOF range before handing it to the quadratic sieve. Rho finds factors
+/// of up to about twice this many bits within the budget; larger factors
+/// are found faster by the sieve, whose cost depends only on the cofactor.
+const RHO_ITERATION_LIMIT: u128 = 1 << 14;
+
+/// The stage 1 and stage 2 bounds of the Pollard p−1 pass that runs before
+/// rho. It is cheap next to the sieve and catches any factor p with a
+/// smooth p − 1, however large p is.
+const PM1_B1: u64 = 1_000;
+const PM1_B2: u64 = 50_000;
+
+/// The number of Fermat steps tried on a cofactor left by trial division.
+/// A product of two primes that agree in their upper half of bits is split
+/// within the first step or two; the budget reaches somewhat further apart
+/// at the cost of a few cheap square tests.
+const FERMAT_ITERATIONS: u128 = 64;
+
+/// Why [`Factorization::factorize`] cannot fail: every cofactor of a
+/// [`Factorable`] type fits in `u128`, where the last resort always splits.
+const FACTORABLE_ALWAYS_FACTORS: &str = "a value that fits in u128 always factors";
+
+/// The default ceiling below which [`Primes`] enumerates with the
+/// segmented sieve. The sieve keeps the primes up to √ceiling, about 82000
+/// of them at the default of 2⁴⁰, and beyond that the per-segment cost of
+/// crossing them off outgrows the primality tests it replaces.
+const SIEVE_CEILING: u64 = 1 << 40;
+
+/// The number of primes [`Primes`] yields by testing wheel candidates
+/// before it starts the sieve. Finding the next prime or two, as
+/// `next_prime` does, is cheaper with a few primality tests than with
+/// setting up the sieve, which pays off once the iterator is enumerating.
+const SIEVE_HANDOVER: usize = 64;
+
 /// A prime factor with its exponent (e.g., 2^3 means integer=2, exponent=3).
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
-pub struct IntFactor {
-    pub integer: u128,
+pub struct Factor<T> {
+    pub integer: T,
     pub exponent: u32,
 }
 
-impl IntFactor {
+/// A factor of a `u128` value.
+pub type IntFactor = Factor<u128>;
+
+impl<T: PrimeInt> Factor<T> {
     #[must_use]
-    pub fn to_vec(&self) -> Vec<u128> {
+    pub fn to_vec(&self) -> Vec<T> {
         vec![self.integer; self.exponent as usize]
     }
 }
 
-impl fmt::Display for IntFactor {
+impl<T: PrimeInt> fmt::Display for Factor<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.exponent > 1 {
             write!(f, "{}^{}", self.integer, self.exponent)
@@ -41,25 +107,112 @@ impl fmt::Display for IntFactor {
     }
 }
 
+/// Parse a factor as [`Factor`]'s `Display` writes it: `p` or `p^e`, with
+/// a prime p and an exponent e of at least 1.
+impl<T: PrimeInt> FromStr for Factor<T> {
+    type Err = ParseFactorsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (base, exponent) = match s.split_once('^') {
+            Some((base, exponent)) => {
+                let e = exponent.trim().parse();
+                (base.trim(), e.map_err(|_| ParseFactorsError::InvalidExponent(s.to_string()))?)
+            }
+            None => (s, 1),
+        };
+        let integer =
+            parse_decimal(base).ok_or_else(|| ParseFactorsError::InvalidNumber(base.to_string()))?;
+        Factor::checked(integer, exponent)
+    }
+}
+
+impl<T: PrimeInt> Factor<T> {
+    /// Check that the integer is prime, the exponent at least 1 and the
+    /// power within the type.
+    pub(crate) fn checked(integer: T, exponent: u32) -> Result<Self, ParseFactorsError> {
+        if exponent == 0 {
+            return Err(ParseFactorsError::InvalidExponent(format!("{integer}^0")));
+        }
+        if !integer.is_prime() {
+            return Err(ParseFactorsError::NotPrime(integer.to_string()));
+        }
+        if checked_pow(integer, exponent).is_none() {
+            return Err(ParseFactorsError::Overflow);
+        }
+        Ok(Factor { integer, exponent })
+    }
+}
+
+/// The error from parsing a [`Factor`] or a [`Factorization`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseFactorsError {
+    /// A base is not a decimal number that fits the type.
+    InvalidNumber(String),
+    /// An exponent is not a decimal number from 1 up.
+    InvalidExponent(String),
+    /// A base is not prime.
+    NotPrime(String),
+    /// A factor does not have a larger base than the one before it.
+    NotAscending(String),
+    /// The value does not fit the type.
+    Overflow,
+}
+
+impl fmt::Display for ParseFactorsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidNumber(s) => write!(f, "invalid number '{s}'"),
+            Self::InvalidExponent(s) => write!(f, "invalid exponent in '{s}'"),
+            Self::NotPrime(s) => write!(f, "{s} is not prime"),
+            Self::NotAscending(s) => write!(f, "factor '{s}' is out of ascending order"),
+            Self::Overflow => f.write_str("the value does not fit the type"),
+        }
+    }
+}
+
+impl std::error::Error for ParseFactorsError {}
+
+/// Parse a non-empty string of decimal digits, or `None` if it has any
+/// other character or does not fit the type.
+fn parse_decimal<T: PrimeInt>(s: &str) -> Option<T> {
+    if s.is_empty() {
+        return None;
+    }
+    s.bytes().try_fold(T::ZERO, |acc, b| {
+        let digit = b.checked_sub(b'0').filter(|&d| d < 10)?;
+        acc.checked_mul(T::from(10))?.checked_add(T::from(digit))
+    })
+}
+
 /// The prime factorization of an integer, represented as a list of
 /// prime factors with their exponents.
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
-pub struct PrimeFactors {
-    factors: Vec<IntFactor>
+pub struct Factorization<T> {
+    factors: Vec<Factor<T>>
 }
 
-impl PrimeFactors {
+/// The factorization of a `u128` value.
+pub type PrimeFactors = Factorization<u128>;
+
+impl<T: PrimeInt> Factorization<T> {
     fn new() -> Self {
-        PrimeFactors { factors: Vec::with_capacity(8) }
+        Factorization { factors: Vec::with_capacity(8) }
     }
-    fn add(&mut self, integer: u128, exponent: u32) {
-        self.factors.push(IntFactor { integer, exponent })
+    fn add(&mut self, integer: T, exponent: u32) {
+        self.factors.push(Factor { integer, exponent })
+    }
+    /// Add a sorted run of primes, grouping repeated ones into exponents.
+    fn add_sorted(&mut self, primes: &[T]) {
+        for chunk in primes.chunk_by(|a, b| a == b) {
+            self.add(chunk[0], chunk.len() as u32);
+        }
     }
     /// Reconstruct the original integer from its prime factorization.
     /// An empty factorization yields 1 (the empty product).
     #[must_use]
-    pub fn value(&self) -> u128 {
-        self.factors.iter().map(|f| f.integer.pow(f.exponent)).product()
+    pub fn value(&self) -> T {
+        self.factors.iter().fold(T::ONE, |acc, f| acc * f.integer.pow(f.exponent))
     }
     /// Return the number of distinct prime factors.
     #[must_use]
@@ -81,19 +234,19 @@ impl PrimeFactors {
     }
     /// Return a slice of the prime factors with exponents.
     #[must_use]
-    pub fn factors(&self) -> &[IntFactor] {
+    pub fn factors(&self) -> &[Factor<T>] {
         &self.factors
     }
     /// Expand the factorization into a flat vector of prime factors.
     #[must_use]
-    pub fn to_vec(&self) -> Vec<u128> {
-        self.factors.iter().flat_map(IntFactor::to_vec).collect()
+    pub fn to_vec(&self) -> Vec<T> {
+        self.factors.iter().flat_map(Factor::to_vec).collect()
     }
     /// Compute the GCD of two prime factorizations by intersecting common factors.
     /// Returns an empty result if either factorization is empty.
     #[must_use]
-    pub fn gcd(&self, other: &PrimeFactors) -> PrimeFactors {
-        let mut pf = PrimeFactors::new();
+    pub fn gcd(&self, other: &Self) -> Self {
+        let mut pf = Self::new();
         if self.is_empty() || other.is_empty() { return pf; }
         let mut s_it = self.factors.iter();
         let mut o_it = other.factors.iter();
@@ -116,14 +269,164 @@ impl PrimeFactors {
         }
         pf
     }
+    /// Compute the LCM of two prime factorizations by merging their factors
+    /// with the larger exponent. An empty factorization acts as 1.
+    #[must_use]
+    pub fn lcm(&self, other: &Self) -> Self {
+        let mut pf = Self::new();
+        let mut s_it = self.factors.iter().peekable();
+        let mut o_it = other.factors.iter().peekable();
+        loop {
+            let next = match (s_it.peek().copied(), o_it.peek().copied()) {
+                (Some(s), Some(o)) => match s.integer.cmp(&o.integer) {
+                    Ordering::Equal => {
+                        s_it.next();
+                        o_it.next();
+                        Some(Factor { exponent: max(s.exponent, o.exponent), ..*s })
+                    }
+                    Ordering::Less => s_it.next().copied(),
+                    Ordering::Greater => o_it.next().copied(),
+                },
+                (Some(_), None) => s_it.next().copied(),
+                (None, Some(_)) => o_it.next().copied(),
+                (None, None) => break,
+            };
+            pf.factors.extend(next);
+        }
+        pf
+    }
+    /// Return the number of divisors, τ(n). An empty factorization is
+    /// taken as 1, here and in the other arithmetic functions below.
+    #[must_use]
+    pub fn divisor_count(&self) -> T {
+        self.factors.iter().fold(T::ONE, |acc, f| acc * T::from_u128(u128::from(f.exponent) + 1))
+    }
+    /// Return the sum of the k-th powers of the divisors, σₖ(n), or `None`
+    /// on overflow.
+    #[must_use]
+    pub fn checked_divisor_sum(&self, k: u32) -> Option<T> {
+        self.factors.iter().try_fold(T::ONE, |acc, f| {
+            let pk = checked_pow(f.integer, k)?;
+            // 1 + pᵏ + p²ᵏ + ... + pᵉᵏ
+            let mut term = T::ONE;
+            let mut sum = T::ONE;
+            for _ in 0..f.exponent {
+                term = term.checked_mul(pk)?;
+                sum = sum.checked_add(term)?;
+            }
+            acc.checked_mul(sum)
+        })
+    }
+    /// Return the sum of the k-th powers of the divisors, σₖ(n).
+    ///
+    /// # Panics
+    /// If the result does not fit the type.
+    #[must_use]
+    pub fn divisor_sum(&self, k: u32) -> T {
+        self.checked_divisor_sum(k).expect("divisor_sum overflow")
+    }
+    /// Return Euler's totient φ(n), the count of integers in [1, n] that
+    /// are coprime to n.
+    #[must_use]
+    pub fn euler_phi(&self) -> T {
+        self.factors.iter().fold(T::ONE, |acc, f| {
+            acc * f.integer.pow(f.exponent - 1) * (f.integer - T::ONE)
+        })
+    }
+    /// Return the Carmichael function λ(n), the exponent of the
+    /// multiplicative group modulo n.
+    #[must_use]
+    pub fn carmichael_lambda(&self) -> T {
+        self.factors.iter().fold(T::ONE, |acc, f| {
+            // λ(2ᵉ) is half of φ(2ᵉ) from 2³ on; λ(pᵉ) = φ(pᵉ) otherwise
+            let mut lambda = f.integer.pow(f.exponent - 1) * (f.integer - T::ONE);
+            if f.integer == T::from(2) && f.exponent > 2 {
+                lambda /= T::from(2);
+            }
+            acc.lcm(lambda)
+        })
+    }
+    /// Return the Möbius function μ(n): 0 if n has a square factor, and
+    /// otherwise −1 to the number of its prime factors.
+    #[must_use]
+    pub fn mobius(&self) -> i8 {
+        if !self.is_squarefree() {
+            0
+        } else if self.factors.len().is_multiple_of(2) {
+            1
+        } else {
+            -1
+        }
+    }
+    /// Return the Liouville function λ(n): −1 to the number of prime
+    /// factors counted with multiplicity.
+    #[must_use]
+    pub fn liouville(&self) -> i8 {
+        if self.count_factors().is_multiple_of(2) { 1 } else { -1 }
+    }
+    /// Return the radical of n, the product of its distinct prime factors.
+    #[must_use]
+    pub fn radical(&self) -> T {
+        self.factors.iter().fold(T::ONE, |acc, f| acc * f.integer)
+    }
+    /// Check if no prime factor appears more than once.
+    #[must_use]
+    pub fn is_squarefree(&self) -> bool {
+        self.factors.iter().all(|f| f.exponent == 1)
+    }
+    /// Check if every prime factor appears at least twice, that is, if n is
+    /// powerful.
+    #[must_use]
+    pub fn is_squarefull(&self) -> bool {
+        self.factors.iter().all(|f| f.exponent > 1)
+    }
+    /// Iterate over every divisor of n, 1 and n included, in unspecified
+    /// order. Nothing is allocated beyond one exponent per prime factor.
+    /// [`Factorization::divisor_count`] and [`Factorization::divisor_sum`]
+    /// give the count and sum without iterating.
+    #[must_use]
+    pub fn divisors(&self) -> Divisors<'_, T> {
+        Divisors::new(&self.factors, false)
+    }
+    /// Iterate over the divisors of n below n, in unspecified order.
+    #[must_use]
+    pub fn proper_divisors(&self) -> Divisors<'_, T> {
+        Divisors::new(&self.factors, true)
+    }
+    /// Return every divisor of n in ascending order.
+    #[must_use]
+    pub fn sorted_divisors(&self) -> Vec<T> {
+        let mut divisors: Vec<T> = self.divisors().collect();
+        divisors.sort_unstable();
+        divisors
+    }
+    /// Return the number of divisors below n, τ(n) − 1.
+    #[must_use]
+    pub fn proper_divisor_count(&self) -> T {
+        self.divisor_count() - T::ONE
+    }
+    /// Return the sum of the divisors below n, σ(n) − n, or `None` on
+    /// overflow.
+    #[must_use]
+    pub fn checked_proper_divisor_sum(&self) -> Option<T> {
+        Some(self.checked_divisor_sum(1)? - self.value())
+    }
+    /// Return the sum of the divisors below n, σ(n) − n.
+    ///
+    /// # Panics
+    /// If σ(n) does not fit the type.
+    #[must_use]
+    pub fn proper_divisor_sum(&self) -> T {
+        self.checked_proper_divisor_sum().expect("proper_divisor_sum overflow")
+    }
     /// Check if n has any non-trivial factor using wheel factorization.
     /// Returns true as soon as any factor is found, without full decomposition.
     #[must_use]
-    pub fn has_any_factor(n: u128) -> bool {
-        if n < 4 { return false; }
-        let pw_iter = PrimeWheel::new();
+    pub fn has_any_factor(n: T) -> bool {
+        if n < T::from(4) { return false; }
+        let pw_iter = Wheel210::new();
         for f in pw_iter {
-            if f * f > n {
+            if f > n / f {
                 return false;
             }
             if n.is_multiple_of(f) {
@@ -132,31 +435,50 @@ impl PrimeFactors {
         }
         false
     }
-    /// Compute the prime factorization of n using wheel factorization.
+    /// Compute the prime factorization of n, as [`Factorization::factorize`]
+    /// does, or `None` if it is out of reach.
+    ///
+    /// This is always `Some` for `u32`, `u64` and `u128`. A [`U256`]
+    /// cofactor above `u128` can only be split with Pollard's rho, which is
+    /// given a budget that reaches factors of about 48 bits; when it
+    /// exhausts the budget the result is `None` rather than an endless
+    /// search.
     #[must_use]
-    pub fn factorize(n: u128) -> Self {
+    pub fn try_factorize(n: T) -> Option<Self> {
+        // A value of a wider type that fits in u128 is factored as a u128
+        if T::BITS > 128 && let Some(m) = n.to_u128() {
+            return Some(Self::widen(PrimeFactors::factorize(m)));
+        }
         // If the number is large, we enable the Miller-Rabin fast paths
-        if n > MR_TRIAL_DIVISION_CROSSOVER {
+        if exceeds(n, MR_TRIAL_DIVISION_CROSSOVER) {
             Self::factorize_large(n)
         } else {
             // Hot path for small numbers: 100% pure trial division, no MR overhead
-            Self::factorize_small(n)
+            Some(Self::factorize_small(n))
         }
     }
     #[inline]
-    fn factorize_large(n: u128) -> Self {
-        let mut pf = PrimeFactors::new();
-        if n < 2 { return pf; }
+    fn factorize_large(n: T) -> Option<Self> {
+        Self::factorize_with(n, &PrimeFactors::find_factor)
+    }
+    /// Trial divide n by small primes, then split the remaining cofactor
+    /// into primes with the given method. `split` is only called on
+    /// composites and must return a proper factor. Returns `None` if a
+    /// cofactor too wide for `split` cannot be split.
+    fn factorize_with(n: T, split: &impl Fn(u128) -> u128) -> Option<Self> {
+        let mut pf = Self::new();
+        if n < T::from(2) { return Some(pf); }
         let mut maxsq = n;
         let mut x = n;
         // --- 1. EARLY EXIT FOR PRIMES ---
-        if u128_is_prime(n) {
+        if n.is_prime() {
             pf.add(n, 1);
-            return pf;
+            return Some(pf);
         }
-        let pw_iter = PrimeWheel::new();
+        let limit = T::from_u128(TRIAL_DIVISION_LIMIT);
+        let pw_iter = Wheel210::new();
         for f in pw_iter {
-            if f * f > maxsq { break; }
+            if f * f > maxsq || f > limit { break; }
             let mut c = 0;
             while x.is_multiple_of(f) {
                 x /= f;
@@ -166,26 +488,58 @@ impl PrimeFactors {
                 maxsq = x;
                 pf.add(f, c);
                 // --- 2. EARLY EXIT FOR INTERMEDIATE CHUNKS ---
-                if x > MR_TRIAL_DIVISION_CROSSOVER && u128_is_prime(x) {
+                if exceeds(x, MR_TRIAL_DIVISION_CROSSOVER) && x.is_prime() {
                     pf.add(x, 1);
-                    x = 1;
+                    x = T::ONE;
                     break;
                 }
             }
-            if x == 1 { break; }
+            if x == T::ONE { break; }
         }
-        if x > 1 {
-            pf.add(x, 1);
+        if x > T::ONE {
+            // --- 3. FERMAT / SQUFOF / P-1 / RHO / SIQS FOR THE REMAINING COFACTOR ---
+            // All remaining prime factors exceed the trial division limit,
+            // so they sort after every factor recorded so far.
+            let mut primes = Vec::new();
+            Self::split_cofactor(x, split, &mut primes)?;
+            primes.sort_unstable();
+            pf.add_sorted(&primes);
         }
-        pf
+        Some(pf)
+    }
+    /// Recursively split a cofactor free of small factors into primes.
+    /// The primality tests run at the native width; only the splitting
+    /// methods work on `u128`.
+    fn split_cofactor(n: T, split: &impl Fn(u128) -> u128, primes: &mut Vec<T>) -> Option<()> {
+        if n == T::ONE { return Some(()); }
+        if !exceeds(n, TRIAL_DIVISION_LIMIT * TRIAL_DIVISION_LIMIT) || n.is_prime() {
+            primes.push(n);
+            return Some(());
+        }
+        // Products of two nearly equal primes are the worst case for the
+        // other methods but are split by Fermat's method in a few steps.
+        // Cofactors too wide for the u128 methods are split with rho.
+        let d = match n.to_u128() {
+            Some(m) => T::from_u128(fermat::find_factor(m, FERMAT_ITERATIONS).unwrap_or_else(|| split(m))),
+            None => n.split_wide()?,
+        };
+        Self::split_cofactor(d, split, primes)?;
+        Self::split_cofactor(n / d, split, primes)
+    }
+    /// Convert the factorization of a value that fits in u128.
+    fn widen(pf: PrimeFactors) -> Self {
+        let factors = pf.factors.into_iter()
+            .map(|f| Factor { integer: T::from_u128(f.integer), exponent: f.exponent })
+            .collect();
+        Factorization { factors }
     }
     #[inline]
-    fn factorize_small(n: u128) -> Self {
-        let mut pf = PrimeFactors::new();
-        if n < 2 { return pf; }
+    fn factorize_small(n: T) -> Self {
+        let mut pf = Self::new();
+        if n < T::from(2) { return pf; }
         let mut maxsq = n;
         let mut x = n;
-        let pw_iter = PrimeWheel::new();
+        let pw_iter = Wheel210::new();
         for f in pw_iter {
             if f * f > maxsq { break; }
             let mut c = 0;
@@ -197,16 +551,65 @@ impl PrimeFactors {
                 maxsq = x;
                 pf.add(f, c);
             }
-            if x == 1 { break; }
+            if x == T::ONE { break; }
         }
-        if x > 1 {
+        if x > T::ONE {
             pf.add(x, 1);
         }
         pf
     }
 }
 
-impl fmt::Display for PrimeFactors {
+impl<T: Factorable> Factorization<T> {
+    /// Compute the prime factorization of n using wheel factorization.
+    ///
+    /// Large inputs are trial divided only up to a small bound; whatever
+    /// cofactor remains first gets a few steps of Fermat's method (see
+    /// [`fermat`]), then is split with SQUFOF (see [`squfof`]) when it fits
+    /// in 62 bits. Larger cofactors get Pollard's p−1 with small bounds and
+    /// a short run of Pollard's rho (see [`pollard`]), and if those find
+    /// nothing, the self-initialising quadratic sieve (see [`siqs`]).
+    ///
+    /// Not available for [`U256`], whose values may be out of reach; use
+    /// [`Factorization::try_factorize`] for it.
+    #[must_use]
+    pub fn factorize(n: T) -> Self {
+        Self::try_factorize(n).expect(FACTORABLE_ALWAYS_FACTORS)
+    }
+    /// Compute the prime factorization of n, splitting any cofactor left
+    /// after trial division with the elliptic curve method (see [`ecm`]).
+    ///
+    /// Cofactors that ECM cannot split within the given bounds fall back to
+    /// the same methods as [`Factorization::factorize`], so the result is
+    /// always a complete factorization.
+    #[must_use]
+    pub fn factorize_ecm(n: T, params: &ecm::EcmParams) -> Self {
+        Self::factorize_with(n, &|m| {
+            ecm::find_factor(m, params).unwrap_or_else(|| PrimeFactors::find_factor(m))
+        }).expect(FACTORABLE_ALWAYS_FACTORS)
+    }
+}
+
+impl PrimeFactors {
+    /// Compute the prime factorization of a signed integer, recording the
+    /// sign of a negative n as a unit factor of −1.
+    #[must_use]
+    pub fn factorize_signed(n: i128) -> SignedFactors {
+        SignedFactors { negative: n < 0, factors: Self::factorize(n.unsigned_abs()) }
+    }
+    /// Find a proper factor of a composite cofactor with the default methods.
+    fn find_factor(n: u128) -> u128 {
+        if n < squfof::SQUFOF_LIMIT {
+            return squfof::find_factor(n).unwrap_or_else(|| pollard::find_factor(n));
+        }
+        pollard::find_factor_pm1(n, PM1_B1, PM1_B2)
+            .or_else(|| pollard::find_factor_within(n, RHO_ITERATION_LIMIT))
+            .or_else(|| siqs::find_factor(n))
+            .unwrap_or_else(|| pollard::find_factor(n))
+    }
+}
+
+impl<T: PrimeInt> fmt::Display for Factorization<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for (i, factor) in self.factors.iter().enumerate() {
             if i > 0 {
@@ -218,10 +621,96 @@ impl fmt::Display for PrimeFactors {
     }
 }
 
+/// Parse a factorization as its `Display` writes it, such as
+/// `2^3 * 5 * 7`: prime factors in strictly ascending order, separated by
+/// `*`. The empty string is the factorization of 1.
+impl<T: PrimeInt> FromStr for Factorization<T> {
+    type Err = ParseFactorsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Ok(Self::new());
+        }
+        let factors = s.split('*').map(str::parse).collect::<Result<_, _>>()?;
+        Self::checked(factors)
+    }
+}
+
+impl<T: PrimeInt> Factorization<T> {
+    /// Check that valid factors are in strictly ascending order and that
+    /// their product fits the type.
+    pub(crate) fn checked(factors: Vec<Factor<T>>) -> Result<Self, ParseFactorsError> {
+        let mut value = T::ONE;
+        for (i, factor) in factors.iter().enumerate() {
+            if i > 0 && factors[i - 1].integer >= factor.integer {
+                return Err(ParseFactorsError::NotAscending(factor.to_string()));
+            }
+            value = checked_pow(factor.integer, factor.exponent)
+                .and_then(|power| value.checked_mul(power))
+                .ok_or(ParseFactorsError::Overflow)?;
+        }
+        Ok(Factorization { factors })
+    }
+}
+
+/// The prime factorization of a signed integer: a unit of −1 for negative
+/// values, and the prime factorization of the absolute value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SignedFactors {
+    negative: bool,
+    factors: PrimeFactors,
+}
+
+impl SignedFactors {
+    /// Reconstruct the original integer from its sign and prime factors.
+    #[must_use]
+    pub fn value(&self) -> i128 {
+        // The absolute value of an i128 always fits, and -2^127 wraps to itself
+        let abs = self.factors.value() as i128;
+        if self.negative { abs.wrapping_neg() } else { abs }
+    }
+    /// Return true if the factorization includes the unit −1.
+    #[must_use]
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+    /// Return the prime factorization of the absolute value.
+    #[must_use]
+    pub fn abs(&self) -> &PrimeFactors {
+        &self.factors
+    }
+    /// Compute the GCD of the absolute values, ignoring the signs.
+    #[must_use]
+    pub fn gcd(&self, other: &Self) -> PrimeFactors {
+        self.factors.gcd(&other.factors)
+    }
+    /// Compute the LCM of the absolute values, ignoring the signs.
+    #[must_use]
+    pub fn lcm(&self, other: &Self) -> PrimeFactors {
+        self.factors.lcm(&other.factors)
+    }
+}
+
+impl From<PrimeFactors> for SignedFactors {
+    fn from(factors: PrimeFactors) -> Self {
+        SignedFactors { negative: false, factors }
+    }
+}
+
+impl fmt::Display for SignedFactors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.negative, self.factors.is_empty()) {
+            (true, true) => f.write_str("-1"),
+            (true, false) => write!(f, "-1 * {}", self.factors),
+            (false, _) => write!(f, "{}", self.factors),
+        }
+    }
+}
+
 /// Iterate over the prime factors with their exponents.
-impl<'a> IntoIterator for &'a PrimeFactors {
-    type Item = &'a IntFactor;
-    type IntoIter = std::slice::Iter<'a, IntFactor>;
+impl<'a, T> IntoIterator for &'a Factorization<T> {
+    type Item = &'a Factor<T>;
+    type IntoIter = std::slice::Iter<'a, Factor<T>>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.factors.iter()
@@ -229,46 +718,137 @@ impl<'a> IntoIterator for &'a PrimeFactors {
 }
 
 /// Consume and iterate over the prime factors with their exponents.
-impl IntoIterator for PrimeFactors {
-    type Item = IntFactor;
-    type IntoIter = std::vec::IntoIter<IntFactor>;
+impl<T> IntoIterator for Factorization<T> {
+    type Item = Factor<T>;
+    type IntoIter = std::vec::IntoIter<Factor<T>>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.factors.into_iter()
     }
 }
 
+/// An iterator over the divisors of a [`Factorization`], created by
+/// [`Factorization::divisors`] and [`Factorization::proper_divisors`].
+///
+/// It counts through the exponents of the prime factors like the digits
+/// of a mixed-radix number, updating the divisor with one multiplication
+/// or division per step.
+#[derive(Clone, Debug)]
+pub struct Divisors<'a, T> {
+    factors: &'a [Factor<T>],
+    exponents: Vec<u32>,
+    divisor: T,
+    /// Whether to stop before n itself, the last divisor in the order.
+    proper: bool,
+    done: bool,
+}
+
+impl<'a, T: PrimeInt> Divisors<'a, T> {
+    fn new(factors: &'a [Factor<T>], proper: bool) -> Self {
+        Divisors { factors, exponents: vec![0; factors.len()], divisor: T::ONE, proper, done: false }
+    }
+}
+
+impl<T: PrimeInt> Iterator for Divisors<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let divisor = self.divisor;
+        // Advance to the next divisor; a carry out of the last digit ends it
+        self.done = true;
+        for (f, e) in self.factors.iter().zip(self.exponents.iter_mut()) {
+            if *e < f.exponent {
+                *e += 1;
+                self.divisor *= f.integer;
+                self.done = false;
+                break;
+            }
+            self.divisor /= f.integer.pow(*e);
+            *e = 0;
+        }
+        if self.done && self.proper {
+            return None;
+        }
+        Some(divisor)
+    }
+}
+
 /// An iterator that yields prime numbers in ascending order.
 /// Uses wheel factorization to generate candidates, filtering
 /// with Miller-Rabin (when available) for fast primality testing.
+/// Once it has yielded a few primes below the sieve ceiling, it switches
+/// to a segmented sieve (see [`sieve`]) up to the ceiling, and back to
+/// the wheel above it. It ends at the largest prime that fits in `T`.
 #[derive(Clone, Debug)]
-pub struct PrimeNumbers {
-    wheel: PrimeWheel,
+pub struct Primes<T> {
+    wheel: Wheel210<T>,
+    sieve: Option<sieve::SegmentedSieve>,
+    ceiling: u64,
+    yielded: usize,
 }
 
-impl PrimeNumbers {
+/// An iterator over `u128` primes in ascending order.
+pub type PrimeNumbers = Primes<u128>;
+
+impl<T: PrimeInt> Primes<T> {
     #[must_use]
     pub fn new() -> Self {
-        Self { wheel: PrimeWheel::new() }
+        Self::from(T::ZERO)
     }
     /// Create an iterator that yields primes >= `start`.
     #[must_use]
-    pub fn from(start: u128) -> Self {
-        Self { wheel: PrimeWheel::from(start) }
+    pub fn from(start: T) -> Self {
+        Self { wheel: Wheel210::from(start), sieve: None, ceiling: SIEVE_CEILING, yielded: 0 }
+    }
+    /// Set the value below which primes are enumerated with the segmented
+    /// sieve; 0 disables the sieve. The default is 2⁴⁰.
+    #[must_use]
+    pub fn sieve_ceiling(mut self, ceiling: u64) -> Self {
+        self.ceiling = ceiling;
+        self
+    }
+    /// The end of the sieved range: the ceiling, or `T::MAX` if that is
+    /// lower, which is never prime.
+    fn sieve_end(&self) -> u64 {
+        T::MAX.to_u128().map_or(self.ceiling, |max| max.min(self.ceiling.into()) as u64)
+    }
+    /// Hand over to the sieve after `p`, if the iterator is still below the
+    /// end of the sieved range.
+    fn start_sieve(&mut self, p: T) {
+        let end = self.sieve_end();
+        if let Some(p) = p.to_u128() && p < u128::from(end) {
+            self.sieve = Some(sieve::SegmentedSieve::new(p as u64 + 1..end));
+        }
     }
 }
 
-impl Default for PrimeNumbers {
+impl<T: PrimeInt> Default for Primes<T> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl Iterator for PrimeNumbers {
-    type Item = u128;
+impl<T: PrimeInt> Iterator for Primes<T> {
+    type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.wheel.by_ref().find(|&n| u128_is_prime(n))
+        if let Some(sieve) = &mut self.sieve {
+            if let Some(p) = sieve.next() {
+                return Some(T::from_u128(p.into()));
+            }
+            // Continue with the wheel from the end of the sieve
+            self.wheel = Wheel210::from(T::from_u128(self.sieve_end().into()));
+            self.sieve = None;
+        }
+        let p = self.wheel.by_ref().find(|&n| n.is_prime())?;
+        self.yielded += 1;
+        if self.yielded == SIEVE_HANDOVER {
+            self.start_sieve(p);
+        }
+        Some(p)
     }
 }
 
@@ -276,81 +856,132 @@ impl Iterator for PrimeNumbers {
 /// Uses wheel factorization to traverse candidates backwards, filtering
 /// with Miller-Rabin (when available) for fast primality testing.
 #[derive(Clone, Debug)]
-pub struct DescendingPrimes {
-    wheel: PrimeWheel,
+pub struct PrimesDescending<T> {
+    wheel: Wheel210<T>,
 }
 
-impl DescendingPrimes {
+/// An iterator over `u128` primes in descending order.
+pub type DescendingPrimes = PrimesDescending<u128>;
+
+impl<T: PrimeInt> PrimesDescending<T> {
     /// Create an iterator that yields primes `<= start`.
     #[must_use]
-    pub fn from(start: u128) -> Self {
-        Self { wheel: PrimeWheel::from(start.saturating_add(1)) }
+    pub fn from(start: T) -> Self {
+        let start = start.checked_add(T::ONE).unwrap_or(T::MAX);
+        Self { wheel: Wheel210::from(start) }
     }
 }
 
-impl Iterator for DescendingPrimes {
-    type Item = u128;
+impl<T: PrimeInt> Iterator for PrimesDescending<T> {
+    type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             let candidate = self.wheel.prev()?;
-            if u128_is_prime(candidate) {
+            if candidate.is_prime() {
                 return Some(candidate);
             }
         }
     }
 }
 
+/// How [`u128_is_prime_with`] decides on values above the range where
+/// Miller-Rabin is deterministic (about 82 bits).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PrimalityTest {
+    /// Prove every probable prime with a [`PrimalityCertificate`], so the
+    /// answer is always correct.
+    #[default]
+    Proven,
+    /// Accept the [`bpsw::bpsw`] test as the final answer. No composite is
+    /// known to pass it, and it takes microseconds at any size.
+    Bpsw,
+}
+
 /// Test if the value is a prime number.
 ///
 /// Uses deterministic Miller-Rabin for numbers below `MR_DETERMINISTIC_LIMIT`
 /// (proven correct). For larger values, Miller-Rabin is used as a fast composite
-/// filter (it has no false negatives), and any candidate that passes is verified
-/// via trial-division factorization — guaranteeing correctness for all u128.
+/// filter (it has no false negatives), and any candidate that passes is proven
+/// prime with a [`PrimalityCertificate`] — guaranteeing correctness for all u128.
 ///
-/// Note: for very large primes (above the Miller-Rabin threshold), the
-/// factorization fallback may be slow.
+/// Note: building the certificate means factoring n − 1. For 128-bit
+/// primes that takes about 2 ms at the median and up to about 150 ms when
+/// n − 1 has two large prime factors that need the quadratic sieve, in a
+/// release build. See [`u128_is_prime_with`] for a faster alternative.
 #[must_use]
 pub fn u128_is_prime(n: u128) -> bool {
-    if !is_prime_candidate(n) { return false; }
+    u128_is_prime_with(n, PrimalityTest::Proven)
+}
+
+/// Test if the value is a prime number, choosing how values above
+/// `MR_DETERMINISTIC_LIMIT` are decided. Below the limit, the answer is the
+/// same as [`u128_is_prime`] for either test.
+#[must_use]
+pub fn u128_is_prime_with(n: u128, test: PrimalityTest) -> bool {
+    if let Some(prime) = small_prime_test(n) {
+        return prime;
+    }
+    if n < MR_DETERMINISTIC_LIMIT {
+        return miller_rabin(n);
+    }
+    match test {
+        PrimalityTest::Bpsw => bpsw::bpsw(n),
+        PrimalityTest::Proven => {
+            // MR has no false negatives: if it says composite, it is composite.
+            if !miller_rabin(n) { return false; }
+            // Prove the probable prime with a Pocklington certificate, which
+            // fails for any composite.
+            PrimalityCertificate::new(n).is_some()
+        }
+    }
+}
+
+/// bᵉ, or `None` on overflow.
+fn checked_pow<T: PrimeInt>(base: T, exp: u32) -> Option<T> {
+    (0..exp).try_fold(T::ONE, |acc, _| acc.checked_mul(base))
+}
+
+/// Whether n is above a bound given as a u128.
+fn exceeds<T: PrimeInt>(n: T, bound: u128) -> bool {
+    n.to_u128().is_none_or(|n| n > bound)
+}
+
+/// Settle the primality of n when it is below 11 or divisible by a prime
+/// below 100, leaving `None` for Miller-Rabin.
+pub(crate) fn small_prime_test<T: PrimeInt>(n: T) -> Option<bool> {
+    if !is_prime_candidate(n) { return Some(false); }
+    if n < T::from(11) { return Some(true); }
     // Trial division by subsequent small primes. Even though the wheel
     // filters out multiples of 2, 3, 5, and 7, remaining composites are
     // heavily stripped out by small integer division before hitting the 
     // much slower Miller-Rabin steps.
-    const SMALL_PRIMES: &[u128] = &[
+    const SMALL_PRIMES: &[u8] = &[
         11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97
     ];
     for &p in SMALL_PRIMES {
-        if n == p { return true; }
-        if n.is_multiple_of(p) { return false; }
+        let p = T::from(p);
+        if n == p { return Some(true); }
+        if n.is_multiple_of(p) { return Some(false); }
     }
-    if n < MR_DETERMINISTIC_LIMIT {
-        return miller_rabin(n);
-    }
-    // MR has no false negatives: if it says composite, it is composite.
-    if !miller_rabin(n) { return false; }
-    // Verify with guaranteed-correct trial division: if any factor
-    // exists, n is composite. Stops at the first factor found.
-    !PrimeFactors::has_any_factor(n)
+    None
 }
 
 /// Return the smallest prime >= n.
 ///
-/// Uses the [`PrimeNumbers`] iterator starting from `n`.
+/// The `u128` form of [`PrimeInt::next_prime`]. Panics if n is above the
+/// largest `u128` prime.
 #[must_use]
 pub fn next_prime(n: u128) -> u128 {
-    PrimeNumbers::from(n).next().unwrap()
+    PrimeInt::next_prime(n).unwrap()
 }
 
 /// Return the largest prime <= n.
 ///
-/// Uses the [`DescendingPrimes`] iterator starting from `n` and moving backwards.
+/// The `u128` form of [`PrimeInt::prev_prime`].
 #[must_use]
 pub fn prev_prime(n: u128) -> Option<u128> {
-    if n < 2 {
-        return None;
-    }
-    DescendingPrimes::from(n).next()
+    PrimeInt::prev_prime(n)
 }
 
 /// Calculate the Greatest common divisor (GCD) between 2 unsigned integers,
@@ -377,14 +1008,7 @@ pub fn primefactor_gcd(this: u128, that: u128) -> PrimeFactors {
 /// <https://en.wikipedia.org/wiki/Euclidean_algorithm>
 #[must_use]
 pub fn u128_gcd(this: u128, that: u128) -> u128 {
-    let mut a = this;
-    let mut b = that;
-    while b > 0 {
-        let c = b;
-        b = a % b;
-        a = c;
-    }
-    a
+    this.gcd(that)
 }
 
 /// Calculate the Least common multiple (LCM) for 2 integers.
@@ -395,9 +1019,5 @@ pub fn u128_lcm(this: u128, that: u128) -> u128 {
 
 #[must_use]
 pub fn checked_u128_lcm(this: u128, that: u128) -> Option<u128> {
-    if this == 0 || that == 0 {
-        return Some(0);
-    }
-    let gcd = u128_gcd(this, that);
-    (this / gcd).checked_mul(that)
+    this.checked_lcm(that)
 }
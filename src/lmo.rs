@@ -0,0 +1,525 @@
+//! The prime counting function π(x) with the Lagarias–Miller–Odlyzko
+//! method, and the n-th prime found with it.
+//!
+//! With y ≥ ∛x and a = π(y), Meissel's formula gives
+//! π(x) = φ(x, a) + a − 1 − P2(x, a), where φ(x, a) counts the integers
+//! up to x with no prime factor among the first a primes, and P2 counts
+//! those with exactly two prime factors above y. P2 takes one pass of the
+//! segmented sieve over [0, x/y]. φ(x, a) is expanded with the recurrence
+//! φ(x, b) = φ(x, b − 1) − φ(x/p_b, b − 1) into leaves: ordinary ones,
+//! φ(x/n, c) with n ≤ y and a fixed small c, which a table gives directly,
+//! and special ones, φ(x/(m·p), b) with m ≤ y < m·p, whose arguments are
+//! all below x/y. Most special leaves are answered by a second pass of
+//! sieving over [1, x/y], removing one prime after another and counting
+//! what remains with counts kept per block of the bitmap; those whose
+//! argument is below p² and y are answered from a table of π up to y. The
+//! whole computation takes O(x^(2/3)) time and O(x^(1/3)) memory.
+//!
+//! Reference: <https://en.wikipedia.org/wiki/Prime-counting_function#Algorithms_for_evaluating_%CF%80(x)>
+//! Reference: <https://www.ams.org/journals/mcom/1985-44-170/S0025-5718-1985-0777285-5/>
+use crate::sieve::SegmentedSieve;
+use crate::{DescendingPrimes, PrimeNumbers};
+
+/// The exclusive upper bound for [`prime_pi`] and for the primes
+/// [`nth_prime`] returns. It is far beyond what could be counted in any
+/// case, and keeps the intermediate sums well within range.
+pub const PRIME_PI_LIMIT: u128 = 1 << 80;
+
+/// Below this, π(x) is counted directly with the segmented sieve.
+const SIEVE_LIMIT: u128 = 1 << 24;
+
+/// π(2²⁴), the number of primes below the sieve limit.
+const SIEVE_LIMIT_PI: u128 = 1_077_871;
+
+/// The number of small primes (2 to 13) whose multiples the ordinary
+/// leaves remove with a table, and that the sieve removes with word masks.
+const C: usize = 6;
+const PRIMORIAL: u128 = 2 * 3 * 5 * 7 * 11 * 13;
+const PHI_PRIMORIAL: u128 = 2 * 4 * 6 * 10 * 12;
+
+/// The number of odd integers in each segment of the special leaf sieve.
+const SEGMENT_BITS: u64 = 1 << 20;
+
+/// The number of words in a block of the special leaf sieve, and of
+/// blocks in a group, whose counts are kept.
+const BLOCK: usize = 8;
+
+/// Count the primes less than or equal to `x`, or return `None` if `x` is
+/// at least [`PRIME_PI_LIMIT`] (2⁸⁰).
+///
+/// π(10¹⁴) takes about two seconds in a release build, and the time grows
+/// as x^(2/3); the ignored test `test_prime_pi_large` checks the counts at
+/// 10¹⁴ and 10¹⁶.
+#[must_use]
+pub fn prime_pi(x: u128) -> Option<u128> {
+    if x < SIEVE_LIMIT {
+        return Some(SegmentedSieve::new(0..x as u64 + 1).count() as u128);
+    }
+    if x >= PRIME_PI_LIMIT {
+        return None;
+    }
+    let sqrt_x = x.isqrt() as u64;
+    // A y somewhat above ∛x moves work from the sieve to the cheaper leaves
+    let alpha = u64::from(x.ilog2() / 5);
+    let y = (icbrt(x) * alpha).min(sqrt_x);
+    let small = SmallSieve::new(y);
+    let a = small.pi[y as usize] as u128;
+    let phi = ordinary_leaves(x, &small) + special_leaves(x, y, &small);
+    Some((phi + a as i128 - 1) as u128 - p2(x, y, sqrt_x, a))
+}
+
+/// Return the k-th prime, counting from p₁ = 2, or `None` if `k` is 0 or
+/// the k-th prime is at least [`PRIME_PI_LIMIT`].
+///
+/// The estimate R⁻¹(k), from Riemann's prime counting function, is within
+/// a few hundred thousand primes of p_k up to 10¹⁶. [`prime_pi`] counts
+/// the primes up to the estimate, and the iterators walk the rest of the
+/// way from there, so the time is that of one count.
+#[must_use]
+pub fn nth_prime(k: u128) -> Option<u128> {
+    if k == 0 {
+        return None;
+    }
+    // Up to the sieve limit, the sieve behind the iterator is faster
+    if k <= SIEVE_LIMIT_PI {
+        return PrimeNumbers::new().nth(k as usize - 1);
+    }
+    let estimate = riemann_r_inverse(k as f64) as u128;
+    let count = prime_pi(estimate)?;
+    if count >= k {
+        DescendingPrimes::from(estimate).nth((count - k) as usize)
+    } else {
+        PrimeNumbers::from(estimate + 1).nth((k - count - 1) as usize)
+    }
+    .filter(|&p| p < PRIME_PI_LIMIT)
+}
+
+/// The Riemann zeta function ζ(s) for s ≥ 2, as a short sum with an
+/// Euler–Maclaurin correction for its tail.
+fn zeta(s: f64) -> f64 {
+    const N: f64 = 10.0;
+    let head: f64 = (1..10).map(|n| f64::from(n).powf(-s)).sum();
+    head + N.powf(1.0 - s) / (s - 1.0) + N.powf(-s) / 2.0 + s * N.powf(-s - 1.0) / 12.0
+}
+
+/// Riemann's R(x) = 1 + Σ (ln x)ᵏ / (k·k!·ζ(k + 1)), the Gram series.
+fn riemann_r(x: f64) -> f64 {
+    let ln_x = x.ln();
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    for k in 1.. {
+        term *= ln_x / f64::from(k);
+        let next = term / (f64::from(k) * zeta(f64::from(k) + 1.0));
+        sum += next;
+        if next < sum * f64::EPSILON {
+            break;
+        }
+    }
+    sum
+}
+
+/// The x with R(x) = k, by Newton's method, where R'(x) ≈ 1 / ln x.
+fn riemann_r_inverse(k: f64) -> f64 {
+    let mut x = k * k.ln();
+    for _ in 0..100 {
+        let step = (riemann_r(x) - k) * x.ln();
+        x -= step;
+        if step.abs() < 1.0 {
+            break;
+        }
+    }
+    x
+}
+
+/// The integer cube root ⌊∛x⌋.
+fn icbrt(x: u128) -> u64 {
+    let mut r = (x as f64).cbrt() as u64;
+    let cube = |r: u64| (r as u128).checked_pow(3);
+    while cube(r).is_none_or(|c| c > x) {
+        r -= 1;
+    }
+    while cube(r + 1).is_some_and(|c| c <= x) {
+        r += 1;
+    }
+    r
+}
+
+/// ⌊x/d⌋, with a native division when both fit in 64 bits.
+#[inline]
+fn div(x: u128, d: u128) -> u128 {
+    match (u64::try_from(x), u64::try_from(d)) {
+        (Ok(x), Ok(d)) => u128::from(x / d),
+        _ => x / d,
+    }
+}
+
+/// The smallest prime factor, Möbius function and prime counts up to y.
+struct SmallSieve {
+    /// The smallest prime factor of n, with u32::MAX for 1.
+    lpf: Vec<u32>,
+    mu: Vec<i8>,
+    primes: Vec<u64>,
+    pi: Vec<u32>,
+}
+
+impl SmallSieve {
+    /// A linear sieve, which visits each composite once.
+    fn new(y: u64) -> Self {
+        let len = y as usize + 1;
+        let mut lpf = vec![0u32; len];
+        let mut mu = vec![0i8; len];
+        let mut primes = Vec::new();
+        let mut pi = vec![0u32; len];
+        lpf[1] = u32::MAX;
+        mu[1] = 1;
+        for n in 2..len {
+            if lpf[n] == 0 {
+                lpf[n] = n as u32;
+                mu[n] = -1;
+                primes.push(n as u64);
+            }
+            for &p in &primes {
+                let m = n * p as usize;
+                if p as u32 > lpf[n] || m >= len {
+                    break;
+                }
+                lpf[m] = p as u32;
+                mu[m] = if p as u32 == lpf[n] { 0 } else { -mu[n] };
+            }
+            pi[n] = primes.len() as u32;
+        }
+        SmallSieve { lpf, mu, primes, pi }
+    }
+}
+
+/// φ(t, C), from the period of the integers coprime to the first C primes.
+struct PhiTable {
+    table: Vec<u16>,
+}
+
+impl PhiTable {
+    fn new() -> Self {
+        let mut count = 0;
+        let table = (0..PRIMORIAL)
+            .map(|n| {
+                if [2, 3, 5, 7, 11, 13].iter().all(|&p| n % p != 0) {
+                    count += 1;
+                }
+                count
+            })
+            .collect();
+        PhiTable { table }
+    }
+    fn phi(&self, t: u128) -> u128 {
+        t / PRIMORIAL * PHI_PRIMORIAL + u128::from(self.table[(t % PRIMORIAL) as usize])
+    }
+}
+
+/// The sum of μ(n)·φ(x/n, C) over n ≤ y with no prime factor up to p_C.
+fn ordinary_leaves(x: u128, small: &SmallSieve) -> i128 {
+    let table = PhiTable::new();
+    let p_c = small.primes[C - 1] as u32;
+    (1..small.mu.len())
+        .filter(|&n| small.mu[n] != 0 && small.lpf[n] > p_c)
+        .map(|n| i128::from(small.mu[n]) * table.phi(x / n as u128) as i128)
+        .sum()
+}
+
+/// The next special leaf of one prime p = p_(b+1) for the sieve to answer.
+/// The leaves are visited in order of increasing argument, that is, of
+/// decreasing m.
+enum Cursor {
+    /// The squarefree m = rough[i] in (lower, y] for i < next, and with no
+    /// prime factor up to p.
+    Any { next: usize, lower: u64 },
+    /// Primes q = primes[i] for i in [lower, next), when p > √y.
+    Prime { next: usize, lower: usize },
+}
+
+/// The sum of −μ(m)·φ(x/(m·p), b) over the special leaves, for p = p_(b+1)
+/// from p_(C+1) to p_a.
+fn special_leaves(x: u128, y: u64, small: &SmallSieve) -> i128 {
+    let primes = &small.primes;
+    let pi = |n: u64| small.pi[n as usize] as usize;
+    let a = primes.len();
+    let mut sum: i128 = 0;
+    let mut cursors = Vec::new();
+    // The squarefree m ≤ y with no prime factor up to p_C
+    let p_c = primes[C - 1] as u32;
+    let rough: Vec<u32> = (1..=y as u32)
+        .filter(|&m| small.mu[m as usize] != 0 && small.lpf[m as usize] > p_c)
+        .collect();
+    for (b, &p) in primes.iter().enumerate().skip(C) {
+        if p * p <= y {
+            cursors.push(Cursor::Any { next: rough.len(), lower: y / p });
+            continue;
+        }
+        // Here m must be a prime q with p < q ≤ y, and μ(q) = −1. When the
+        // argument v is below p, φ(v, b) = 1; when it is below both p² and
+        // y, φ(v, b) = 1 + π(v) − b. Only larger arguments need the sieve.
+        let bound = (p * p).min(y + 1) as u128;
+        let hard = div(x, p as u128 * bound).min(y as u128) as u64;
+        let trivial = div(x, p as u128 * p as u128).min(y as u128) as u64;
+        let first = pi(hard.max(p));
+        let last = pi(trivial.max(p));
+        for &q in &primes[first..last] {
+            let v = div(x, p as u128 * q as u128) as u64;
+            sum += (1 + pi(v) - b) as i128;
+        }
+        sum += (a - last) as i128;
+        cursors.push(Cursor::Prime { next: first, lower: b + 1 });
+    }
+    // The primes beyond the last one with a hard leaf need not be sieved
+    while let Some(Cursor::Prime { next, lower }) = cursors.last() && next <= lower {
+        cursors.pop();
+    }
+    sum + hard_leaves(x, y, small, &rough, &mut cursors)
+}
+
+/// Answer the special leaves left in `cursors` by sieving [1, x/y] one
+/// segment at a time, removing p_1, p_2, ... in turn and counting what
+/// remains below a leaf from the counts of whole groups of blocks, then of
+/// whole blocks of words, then the popcounts of the last few words.
+fn hard_leaves(
+    x: u128,
+    y: u64,
+    small: &SmallSieve,
+    rough: &[u32],
+    cursors: &mut [Cursor],
+) -> i128 {
+    let z = (x / y as u128) as u64;
+    let primes = &small.primes;
+    let mut sum: i128 = 0;
+    // phi[i] = φ(lo − 1, C + i), the count below the current segment
+    let mut phi = vec![0u64; cursors.len()];
+    let mut segment = Segment::new();
+    let mut lo = 1;
+    while lo <= z {
+        let hi = (lo + 2 * SEGMENT_BITS).min(z + 1);
+        segment.reset(lo, &primes[1..C]);
+        let limit = div(x, lo as u128);
+        for (i, cursor) in cursors.iter_mut().enumerate() {
+            let b = C + i;
+            let p = primes[b];
+            // Every leaf x/(m·p) with m > p is below p², so larger primes
+            // have no leaves left at or after this segment
+            if p as u128 * p as u128 > limit {
+                break;
+            }
+            // Leaves with m at or below `stop` have arguments past the segment
+            let stop = div(x, p as u128 * hi as u128) as u64;
+            let mut counter = Counter::default();
+            let mut leaf = |m: u64| {
+                let v = div(x, m as u128 * p as u128) as u64;
+                (phi[i] + segment.count(&mut counter, v)) as i128
+            };
+            match cursor {
+                Cursor::Any { next, lower } => {
+                    let stop = stop.max(*lower);
+                    while *next > 0 && u64::from(rough[*next - 1]) > stop {
+                        let m = rough[*next - 1] as usize;
+                        if u64::from(small.lpf[m]) > p {
+                            sum -= i128::from(small.mu[m]) * leaf(m as u64);
+                        }
+                        *next -= 1;
+                    }
+                }
+                Cursor::Prime { next, lower } => {
+                    while *next > *lower && primes[*next - 1] > stop {
+                        sum += leaf(primes[*next - 1]);
+                        *next -= 1;
+                    }
+                }
+            }
+            phi[i] += segment.total;
+            segment.cross_off(p, (hi - lo).div_ceil(2));
+        }
+        lo = hi;
+    }
+    sum
+}
+
+/// The number of integers left before a block of a [`Segment`].
+#[derive(Default)]
+struct Counter {
+    block: usize,
+    count: u32,
+}
+
+/// A segment of the special leaf sieve: a bitmap of the odd integers not
+/// yet removed, with bit i for lo + 2i, and the counts of its blocks of
+/// words and of its groups of blocks. The even ones are always removed,
+/// as 2 is the first prime.
+struct Segment {
+    lo: u64,
+    bits: Vec<u64>,
+    blocks: Vec<u32>,
+    groups: Vec<u32>,
+    total: u64,
+}
+
+impl Segment {
+    fn new() -> Self {
+        let words = (SEGMENT_BITS / 64) as usize;
+        Segment {
+            lo: 0,
+            bits: vec![0; words],
+            blocks: vec![0; words / BLOCK],
+            groups: vec![0; words / BLOCK / BLOCK],
+            total: 0,
+        }
+    }
+    /// Start the segment at the odd `lo`, with the multiples of the small
+    /// odd primes, all below 64, removed by masks that repeat every p words.
+    fn reset(&mut self, lo: u64, small_primes: &[u64]) {
+        self.lo = lo;
+        self.bits.fill(u64::MAX);
+        let mut mask = [0u64; 64];
+        for &p in small_primes {
+            let mask = &mut mask[..p as usize];
+            mask.fill(u64::MAX);
+            for i in (self.first_multiple(p)..64 * p).step_by(p as usize) {
+                mask[(i / 64) as usize] &= !(1 << (i % 64));
+            }
+            for chunk in self.bits.chunks_mut(p as usize) {
+                for (word, m) in chunk.iter_mut().zip(mask.iter()) {
+                    *word &= m;
+                }
+            }
+        }
+        for (count, words) in self.blocks.iter_mut().zip(self.bits.chunks(BLOCK)) {
+            *count = words.iter().map(|w| w.count_ones()).sum();
+        }
+        for (count, blocks) in self.groups.iter_mut().zip(self.blocks.chunks(BLOCK)) {
+            *count = blocks.iter().sum();
+        }
+        self.total = self.groups.iter().map(|&c| u64::from(c)).sum();
+    }
+    /// The index of the first odd multiple of p in the segment.
+    fn first_multiple(&self, p: u64) -> u64 {
+        // lo + 2i ≡ 0 (mod p) for i ≡ −lo/2, with 2⁻¹ ≡ (p + 1)/2
+        (p - self.lo % p) * p.div_ceil(2) % p
+    }
+    /// The number of integers left in [lo, v], for values that increase
+    /// from one call to the next, so that the counter only moves forward:
+    /// a group at a time, then a block at a time, then a word at a time.
+    fn count(&self, counter: &mut Counter, v: u64) -> u64 {
+        let idx = (v - self.lo) / 2;
+        let (word, bit) = ((idx / 64) as usize, idx % 64);
+        let block = word / BLOCK;
+        if block / BLOCK > counter.block / BLOCK {
+            let group = counter.block / BLOCK;
+            counter.count += self.blocks[counter.block..(group + 1) * BLOCK].iter().sum::<u32>();
+            counter.count += self.groups[group + 1..block / BLOCK].iter().sum::<u32>();
+            counter.block = block / BLOCK * BLOCK;
+        }
+        counter.count += self.blocks[counter.block..block].iter().sum::<u32>();
+        counter.block = block;
+        let whole: u32 = self.bits[block * BLOCK..word].iter().map(|w| w.count_ones()).sum();
+        u64::from(counter.count + whole + (self.bits[word] & (u64::MAX >> (63 - bit))).count_ones())
+    }
+    /// Remove the multiples of p among the first `len` odd integers.
+    fn cross_off(&mut self, p: u64, len: u64) {
+        let mut idx = self.first_multiple(p);
+        while idx < len {
+            let (word, bit) = ((idx / 64) as usize, idx % 64);
+            let set = (self.bits[word] >> bit) & 1;
+            self.bits[word] &= !(1 << bit);
+            self.blocks[word / BLOCK] -= set as u32;
+            self.groups[word / BLOCK / BLOCK] -= set as u32;
+            self.total -= set;
+            idx += p;
+        }
+    }
+}
+
+/// P2(x, a): the number of integers up to x with exactly two prime
+/// factors p ≤ q, both above y. That is the sum of π(x/p) − π(p) + 1 over
+/// the primes y < p ≤ √x, found by walking p down from √x while counting
+/// primes up to x/p with a sieve that moves up to x/y.
+fn p2(x: u128, y: u64, sqrt_x: u64, a: u128) -> u128 {
+    const WINDOW: u64 = 1 << 20;
+    let z = (x / y as u128) as u64;
+    let mut counter = SegmentedSieve::new(0..z + 1).peekable();
+    let mut count: u128 = 0;
+    let (mut sum, mut k) = (0, 0);
+    let mut hi = sqrt_x + 1;
+    while hi > y + 1 {
+        let lo = hi.saturating_sub(WINDOW).max(y + 1);
+        for p in SegmentedSieve::new(lo..hi).collect::<Vec<_>>().into_iter().rev() {
+            let t = div(x, p as u128) as u64;
+            while counter.next_if(|&q| q <= t).is_some() {
+                count += 1;
+            }
+            sum += count;
+            k += 1;
+        }
+        hi = lo;
+    }
+    // The k primes above y have π(p) = a + 1, ..., a + k
+    sum - k * a - k * (k - 1) / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{icbrt, nth_prime, prime_pi, riemann_r, PRIME_PI_LIMIT};
+    use crate::sieve::SegmentedSieve;
+
+    #[test]
+    fn test_prime_pi_small() {
+        let expected = [0, 0, 1, 2, 2, 3, 3, 4, 4, 4, 4, 5];
+        for (x, &pi) in expected.iter().enumerate() {
+            assert_eq!(prime_pi(x as u128), Some(pi), "pi({x})");
+        }
+        assert_eq!(prime_pi(1_000_000), Some(78498));
+        assert_eq!(icbrt(u128::MAX), 6981463658331);
+        assert_eq!(icbrt(1_000_000_000_000 - 1), 9999);
+    }
+
+    #[test]
+    fn test_prime_pi_matches_sieve() {
+        for x in [1 << 24, 20_000_000, 25_000_003, 29_999_999] {
+            let count = SegmentedSieve::new(0..x + 1).count() as u128;
+            assert_eq!(prime_pi(x.into()), Some(count), "pi({x})");
+        }
+    }
+
+    #[test]
+    fn test_prime_pi_powers_of_ten() {
+        let expected: [u128; 4] = [50847534, 455052511, 4118054813, 37607912018];
+        for (k, &pi) in expected.iter().enumerate() {
+            assert_eq!(prime_pi(10u128.pow(k as u32 + 9)), Some(pi), "pi(10^{})", k + 9);
+        }
+    }
+
+    #[test]
+    fn test_nth_prime() {
+        let small = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29];
+        for (k, &p) in small.iter().enumerate() {
+            assert_eq!(nth_prime(k as u128 + 1), Some(p));
+        }
+        assert_eq!(nth_prime(1_077_871), Some(16_777_213));
+        assert_eq!(nth_prime(1_077_872), Some(16_777_259));
+        let expected: [u128; 4] = [179424673, 2038074743, 22801763489, 252097800623];
+        for (k, &p) in expected.iter().enumerate() {
+            assert_eq!(nth_prime(10u128.pow(k as u32 + 7)), Some(p), "p(10^{})", k + 7);
+        }
+        assert!((riemann_r(1e16) - 279238341360977.2).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_prime_pi_limit() {
+        assert_eq!(prime_pi(PRIME_PI_LIMIT), None);
+        assert_eq!(prime_pi(u128::MAX), None);
+        assert_eq!(nth_prime(0), None);
+        assert_eq!(nth_prime(u128::MAX), None);
+    }
+
+    #[test]
+    #[ignore = "takes about a minute in a release build"]
+    fn test_prime_pi_large() {
+        assert_eq!(prime_pi(10u128.pow(14)), Some(3204941750802));
+        assert_eq!(prime_pi(10u128.pow(16)), Some(279238341033925));
+        assert_eq!(nth_prime(10u128.pow(14)), Some(3475385758524527));
+    }
+}
@@ -3,11 +3,17 @@ use clap::{Command, Arg, parser::ValuesRef};
 use log::{debug, info};
 use rayon::prelude::*;
 use primefactor::{PrimeFactors, PrimeNumbers, DescendingPrimes};
+use primefactor::sieve::FactorSieve;
 
 const APPNAME: &str = env!("CARGO_PKG_NAME");
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
 
+/// Ranges ending below this are factored with the factor sieve. Above it,
+/// the sieve spends more on crossing off its base primes than factoring
+/// each number on its own costs, and the numbers are factored in parallel.
+const FACTOR_SIEVE_CEILING: u128 = 1 << 40;
+
 fn main() {
     let args = Command::new(APPNAME)
         .version(VERSION)
@@ -56,6 +62,14 @@ fn main() {
         }
     }
     for rng in range_vec {
+        // Factor whole ranges with the factor sieve, where it is faster
+        let (beg, end) = (*rng.start(), *rng.end());
+        if !is_next && !is_prev && beg < end && end < FACTOR_SIEVE_CEILING {
+            for (n, factors) in FactorSieve::new(beg as u64..end as u64 + 1) {
+                println!("{}", describe(n.into(), &factors));
+            }
+            continue;
+        }
         let results: Vec<_> = rng.into_par_iter().map(|n| {
             if is_next {
                 let next_p = PrimeNumbers::from(n.saturating_add(1)).next().unwrap();
@@ -68,19 +82,17 @@ fn main() {
                     return format!("No prime strictly less than {n}");
                 }
             }
-            match n {
-                0 | 1 => format!("{n} is neither prime nor composite"),
-                _ => {
-                    let factors = PrimeFactors::factorize(n);
-                    if factors.is_prime() {
-                        format!("{n} is prime!")
-                    } else {
-                        format!("{n} = {factors}")
-                    }
-                }
-            }
+            describe(n, &PrimeFactors::factorize(n))
         }).collect();
         for line in results { println!("{line}"); }
     }
 }
 
+/// Describe n by its factorization.
+fn describe(n: u128, factors: &PrimeFactors) -> String {
+    match n {
+        0 | 1 => format!("{n} is neither prime nor composite"),
+        _ if factors.is_prime() => format!("{n} is prime!"),
+        _ => format!("{n} = {factors}"),
+    }
+}
@@ -0,0 +1,756 @@
+//! Modular arithmetic on `u128` without overflow.
+//!
+//! These are the functions that the primality tests and factoring methods
+//! of the crate are built on: `mod_pow` is the exponentiation behind
+//! Miller-Rabin, with Montgomery multiplication (see [`crate::montgomery`])
+//! for odd moduli above 64 bits. [`Modulus`] fixes a modulus and chooses
+//! that arithmetic once, and [`ModInt`] is a residue that carries its
+//! modulus, for code that reads better with operators. Around them are
+//! the number theory built on that arithmetic: inverses, the Chinese
+//! Remainder Theorem, linear congruences, the Jacobi symbol and square
+//! roots modulo primes.
+//!
+//! Reference: <https://en.wikipedia.org/wiki/Modular_arithmetic>
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use crate::montgomery::Montgomery;
+use crate::{checked_u128_lcm, u128_gcd, PrimeInt};
+
+/// Modular exponentiation: (base^exp) mod modulus.
+/// Odd moduli above 64 bits use Montgomery multiplication; below that the
+/// product of two residues fits in u128 and `mod_mul` is faster.
+///
+/// # Panics
+/// If the modulus is 0.
+#[inline]
+#[must_use]
+pub fn mod_pow(mut base: u128, mut exp: u128, modulus: u128) -> u128 {
+    if modulus == 1 { return 0; }
+    if modulus > u64::MAX as u128
+        && let Some(mont) = Montgomery::new(modulus)
+    {
+        return mont.mod_pow(base, exp);
+    }
+    let mut result: u128 = 1;
+    base %= modulus;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mod_mul(result, base, modulus);
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = mod_mul(base, base, modulus);
+        }
+    }
+    result
+}
+
+/// Modular addition: (a + b) mod m, without overflow.
+/// Requires a < m and b < m.
+#[inline]
+#[must_use]
+pub fn add_mod(a: u128, b: u128, m: u128) -> u128 {
+    debug_assert!(a < m);
+    debug_assert!(b < m);
+    if a >= m - b {
+        a - (m - b)
+    } else {
+        a + b
+    }
+}
+
+/// Modular subtraction: (a - b) mod m, without underflow.
+/// Requires a < m and b < m.
+#[inline]
+#[must_use]
+pub fn sub_mod(a: u128, b: u128, m: u128) -> u128 {
+    debug_assert!(a < m);
+    debug_assert!(b < m);
+    if a >= b {
+        a - b
+    } else {
+        a + (m - b)
+    }
+}
+
+/// Modular multiplication: (a * b) mod m, without overflow.
+/// Uses direct multiplication when the product fits in u128.
+/// For larger products, it uses Russian peasant multiplication.
+///
+/// # Panics
+/// If the modulus is 0.
+#[inline]
+#[must_use]
+pub fn mod_mul(a: u128, b: u128, m: u128) -> u128 {
+    debug_assert!(m > 0);
+    // For small moduli where a*b won't overflow u128, use direct multiplication
+    if a.leading_zeros() + b.leading_zeros() >= 128 {
+        return (a * b) % m;
+    }
+    let mut result: u128 = 0;
+    let mut a = a % m;
+    let mut b = b % m;
+    // Full-range safe fallback for very large moduli.
+    while b > 0 {
+        if b & 1 == 1 {
+            result = add_mod(result, a, m);
+        }
+        b >>= 1;
+        if b > 0 {
+            a = add_mod(a, a, m);
+        }
+    }
+    result
+}
+
+/// The extended Euclidean algorithm: the gcd g of a and b, with Bézout
+/// coefficients x and y such that a·x + b·y = g.
+///
+/// The gcd is the same as [`crate::u128_gcd`], and the coefficients are
+/// the smallest ones, with |x| ≤ b/(2g) and |y| ≤ a/(2g) for a ≠ b, so
+/// they always fit in `i128`.
+///
+/// Reference: <https://en.wikipedia.org/wiki/Extended_Euclidean_algorithm>
+#[must_use]
+pub fn u128_xgcd(a: u128, b: u128) -> (u128, i128, i128) {
+    // The coefficients alternate in sign, so only their magnitudes are
+    // kept; the last ones computed reach b/g and a/g, beyond i128.
+    let (mut r0, mut r1) = (a, b);
+    let (mut s0, mut s1): (u128, u128) = (1, 0);
+    let (mut t0, mut t1): (u128, u128) = (0, 1);
+    let mut steps = 0;
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (s0, s1) = (s1, s0 + q * s1);
+        (t0, t1) = (t1, t0 + q * t1);
+        steps += 1;
+    }
+    // After an even number of steps x ≥ 0 ≥ y, and the other way after odd
+    let (x, y) = (s0 as i128, t0 as i128);
+    if steps % 2 == 0 { (r0, x, -y) } else { (r0, -x, y) }
+}
+
+/// The inverse of a modulo m, or `None` if a and m are not coprime or m
+/// is 0.
+#[must_use]
+pub fn mod_inverse(a: u128, m: u128) -> Option<u128> {
+    if m == 0 {
+        return None;
+    }
+    let (g, x, _) = u128_xgcd(a % m, m);
+    if g != 1 {
+        return None;
+    }
+    Some(if x < 0 { (m - x.unsigned_abs() % m) % m } else { x as u128 % m })
+}
+
+/// Solve the system x ≡ rᵢ (mod mᵢ) with the Chinese Remainder Theorem,
+/// for moduli that need not be coprime.
+///
+/// Returns the solution x with 0 ≤ x < L, where L is the lcm of the
+/// moduli, and L itself; every solution is x plus a multiple of L. Returns
+/// `None` if the congruences contradict each other, if a modulus is 0, or
+/// if L does not fit in `u128`. The empty system is solved by (0, 1).
+///
+/// Reference: <https://en.wikipedia.org/wiki/Chinese_remainder_theorem>
+#[must_use]
+pub fn crt(congruences: &[(u128, u128)]) -> Option<(u128, u128)> {
+    congruences.iter().try_fold((0, 1), |(x, m), &(r, n)| {
+        if n == 0 {
+            return None;
+        }
+        // x + m·k ≡ r (mod n) needs m·k ≡ r − x (mod n), which is solvable
+        // when gcd(m, n) divides r − x, with k unique modulo n/g
+        let g = u128_gcd(m, n);
+        let diff = sub_mod(r % n, x % n, n);
+        if !diff.is_multiple_of(g) {
+            return None;
+        }
+        let lcm = checked_u128_lcm(m, n)?;
+        let n_g = n / g;
+        let k = mod_mul(diff / g, mod_inverse(m / g, n_g)?, n_g);
+        // x < m and k < n/g, so x + m·k < lcm
+        Some((x + m * k, lcm))
+    })
+}
+
+/// Solve the linear congruence a·x ≡ b (mod m), returning every solution
+/// in [0, m) in ascending order.
+///
+/// There are gcd(a, m) solutions when the gcd divides b, spaced m/gcd
+/// apart, and none otherwise; the iterator yields them lazily, since there
+/// may be very many. A modulus of 0 has no solutions.
+#[must_use]
+pub fn solve_linear_congruence(a: u128, b: u128, m: u128) -> CongruenceSolutions {
+    let none = CongruenceSolutions { next: 0, step: 1, remaining: 0 };
+    if m == 0 {
+        return none;
+    }
+    let (a, b) = (a % m, b % m);
+    let g = u128_gcd(a, m);
+    if !b.is_multiple_of(g) {
+        return none;
+    }
+    let step = m / g;
+    match mod_inverse(a / g, step) {
+        Some(inv) => CongruenceSolutions { next: mod_mul(b / g, inv, step), step, remaining: g },
+        None => none,
+    }
+}
+
+/// The solutions of a linear congruence, from [`solve_linear_congruence`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CongruenceSolutions {
+    next: u128,
+    step: u128,
+    remaining: u128,
+}
+
+impl CongruenceSolutions {
+    /// The distance between consecutive solutions, m/gcd(a, m).
+    #[must_use]
+    pub fn step(&self) -> u128 {
+        self.step
+    }
+    /// The number of solutions not yet yielded.
+    #[must_use]
+    pub fn remaining(&self) -> u128 {
+        self.remaining
+    }
+}
+
+impl Iterator for CongruenceSolutions {
+    type Item = u128;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let x = self.next;
+        // The last step would pass m, which may not fit
+        if self.remaining > 0 {
+            self.next += self.step;
+        }
+        Some(x)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match usize::try_from(self.remaining) {
+            Ok(n) => (n, Some(n)),
+            Err(_) => (usize::MAX, None),
+        }
+    }
+}
+
+/// The Jacobi symbol (a/n), for odd n.
+///
+/// It is 0 when a and n share a factor, and otherwise ±1. For prime n it
+/// is the Legendre symbol, but for composite n a value of 1 does not mean
+/// that a is a square modulo n.
+///
+/// # Panics
+/// If n is even.
+///
+/// Reference: <https://en.wikipedia.org/wiki/Jacobi_symbol>
+#[must_use]
+pub fn jacobi(a: u128, n: u128) -> i8 {
+    assert!(n % 2 == 1, "the Jacobi symbol needs an odd modulus");
+    jacobi_symbol(a % n, n)
+}
+
+/// The Jacobi symbol (a/n) for odd n and a < n, at any width.
+pub(crate) fn jacobi_symbol<T: PrimeInt>(mut a: T, mut n: T) -> i8 {
+    let (zero, three) = (T::ZERO, T::from(3));
+    debug_assert!(!n.is_multiple_of(T::from(2)) && a < n);
+    let mut result = 1;
+    while a != zero {
+        let twos = a.trailing_zeros();
+        a >>= twos;
+        // (2/n) = −1 exactly when n ≡ 3, 5 (mod 8)
+        let n8 = n % T::from(8);
+        if twos % 2 == 1 && (n8 == three || n8 == T::from(5)) {
+            result = -result;
+        }
+        // Quadratic reciprocity for odd a and n
+        if a % T::from(4) == three && n % T::from(4) == three {
+            result = -result;
+        }
+        (a, n) = (n % a, a);
+    }
+    if n == T::ONE { result } else { 0 }
+}
+
+/// The Legendre symbol (a/p) for a prime p: 0 when p divides a, 1 when a
+/// is a non-zero square modulo p, and −1 otherwise.
+///
+/// p is not checked for primality; for composite p the result is the
+/// Jacobi symbol, or meaningless for even p other than 2.
+#[must_use]
+pub fn legendre(a: u128, p: u128) -> i8 {
+    if p == 2 {
+        return (a % 2) as i8;
+    }
+    jacobi(a, p)
+}
+
+/// A square root of a modulo a prime p, or `None` if a is not a square
+/// modulo p.
+///
+/// The root r returned is the smaller of the two, r ≤ p − r, and 0 when p
+/// divides a. Tonelli-Shanks finds it in about s²/4 multiplications plus
+/// an exponentiation, where 2ˢ is the largest power of two dividing p − 1;
+/// when s is large compared to the size of p, Cipolla's method, which does
+/// not depend on s, is used instead.
+///
+/// p is not checked for primality. Any root returned for a composite p is
+/// a true square root of a, but the methods may miss the roots that exist,
+/// and then the result is `None`; it is always `None` for an even p other
+/// than 2. Both methods search for a non-residue, which for a prime is
+/// found within a few tries, and they give up after [`NON_RESIDUE_SEARCH`]
+/// candidates, so a composite p cannot make them run for long.
+///
+/// # Panics
+/// If p is less than 2.
+///
+/// Reference: <https://en.wikipedia.org/wiki/Tonelli%E2%80%93Shanks_algorithm>
+/// and <https://en.wikipedia.org/wiki/Cipolla%27s_algorithm>
+#[must_use]
+pub fn sqrt_mod_prime(a: u128, p: u128) -> Option<u128> {
+    assert!(p >= 2, "the modulus must be a prime");
+    if p > 2 && p.is_multiple_of(2) {
+        return None;
+    }
+    let a = a % p;
+    if a < 2 || p == 2 {
+        return Some(a);
+    }
+    if legendre(a, p) != 1 {
+        return None;
+    }
+    let modulus = Modulus::new(p);
+    let s = (p - 1).trailing_zeros();
+    let r = if p % 4 == 3 {
+        // a^((p+1)/4) squares to a·a^((p−1)/2) = a
+        modulus.pow(a, p / 4 + 1)
+    } else if s * s > 8 * (128 - p.leading_zeros()) {
+        cipolla(a, &modulus)?
+    } else {
+        tonelli_shanks(a, &modulus, s)?
+    };
+    // Always true for a prime p, but not for a composite
+    (modulus.mul(r, r) == a).then(|| r.min(p - r))
+}
+
+/// The number of candidates tried in the search for a non-residue before a
+/// square root is given up on. Under the generalised Riemann hypothesis
+/// the least non-residue of a prime p is below 2·(ln p)², about 16,000 for
+/// 128 bits, and in practice it is far smaller.
+pub const NON_RESIDUE_SEARCH: u128 = 1 << 16;
+
+/// Tonelli-Shanks for a quadratic residue a modulo p, with p − 1 = q·2ˢ.
+/// Returns `None` when p turns out not to be prime.
+fn tonelli_shanks(a: u128, modulus: &Modulus, s: u32) -> Option<u128> {
+    let p = modulus.value();
+    let q = (p - 1) >> s;
+    let z = (2..p.min(NON_RESIDUE_SEARCH)).find(|&z| jacobi_symbol(z, p) == -1)?;
+    let mut m = s;
+    let mut c = modulus.pow(z, q);
+    let mut t = modulus.pow(a, q);
+    let mut r = modulus.pow(a, q / 2 + 1);
+    // Invariant: r² = a·t, with t of order 2^i < 2^m
+    while t != 1 {
+        let mut i = 0;
+        let mut t2 = t;
+        while t2 != 1 {
+            t2 = modulus.mul(t2, t2);
+            i += 1;
+            // Only for a composite p can the order of t fail to drop
+            if i >= m {
+                return None;
+            }
+        }
+        let mut b = c;
+        for _ in 0..m - i - 1 {
+            b = modulus.mul(b, b);
+        }
+        m = i;
+        c = modulus.mul(b, b);
+        t = modulus.mul(t, c);
+        r = modulus.mul(r, b);
+    }
+    Some(r)
+}
+
+/// Cipolla's method for a quadratic residue a modulo p: with t² − a a
+/// non-residue w, (t + √w)^((p+1)/2) in GF(p²) is a root of a in GF(p).
+/// Returns `None` when no such t is found.
+fn cipolla(a: u128, modulus: &Modulus) -> Option<u128> {
+    let p = modulus.value();
+    let (t, w) = (1..p.min(NON_RESIDUE_SEARCH))
+        .map(|t| (t, modulus.sub(modulus.mul(t, t), a)))
+        .find(|&(_, w)| jacobi_symbol(w, p) == -1)?;
+    // (x₁ + y₁√w)(x₂ + y₂√w) = x₁x₂ + y₁y₂w + (x₁y₂ + x₂y₁)√w
+    let mul = |(x1, y1): (u128, u128), (x2, y2): (u128, u128)| {
+        let x = modulus.add(modulus.mul(x1, x2), modulus.mul(modulus.mul(y1, y2), w));
+        let y = modulus.add(modulus.mul(x1, y2), modulus.mul(x2, y1));
+        (x, y)
+    };
+    let (mut base, mut acc) = ((t, 1), (1, 0));
+    let mut exp = p / 2 + 1;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            acc = mul(acc, base);
+        }
+        base = mul(base, base);
+        exp >>= 1;
+    }
+    Some(acc.0)
+}
+
+/// A fixed modulus, with the arithmetic chosen for its size: native
+/// products up to 64 bits, and Montgomery multiplication above that when
+/// it is odd.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Modulus {
+    m: u128,
+    mont: Option<Montgomery>,
+}
+
+impl Modulus {
+    /// Prepare arithmetic modulo m.
+    ///
+    /// # Panics
+    /// If m is 0.
+    #[must_use]
+    pub fn new(m: u128) -> Self {
+        assert!(m > 0, "the modulus must be positive");
+        let mont = if m > u64::MAX as u128 { Montgomery::new(m) } else { None };
+        Modulus { m, mont }
+    }
+    /// The modulus m.
+    #[must_use]
+    pub fn value(&self) -> u128 {
+        self.m
+    }
+    /// Reduce a into [0, m).
+    #[must_use]
+    pub fn reduce(&self, a: u128) -> u128 {
+        a % self.m
+    }
+    /// (a + b) mod m, for any a and b.
+    #[must_use]
+    pub fn add(&self, a: u128, b: u128) -> u128 {
+        add_mod(self.reduce(a), self.reduce(b), self.m)
+    }
+    /// (a − b) mod m, for any a and b.
+    #[must_use]
+    pub fn sub(&self, a: u128, b: u128) -> u128 {
+        sub_mod(self.reduce(a), self.reduce(b), self.m)
+    }
+    /// (a · b) mod m, for any a and b.
+    #[must_use]
+    pub fn mul(&self, a: u128, b: u128) -> u128 {
+        match &self.mont {
+            // (aR)·b·R⁻¹ = ab
+            Some(mont) => mont.mul(mont.to_montgomery(a), b % self.m),
+            None => mod_mul(a, b, self.m),
+        }
+    }
+    /// (a^exp) mod m.
+    #[must_use]
+    pub fn pow(&self, a: u128, exp: u128) -> u128 {
+        match &self.mont {
+            Some(mont) => mont.mod_pow(a, exp),
+            None => mod_pow(a, exp, self.m),
+        }
+    }
+    /// The inverse of a modulo m, if a is coprime to m.
+    #[must_use]
+    pub fn inverse(&self, a: u128) -> Option<u128> {
+        mod_inverse(a, self.m)
+    }
+    /// The residue of a modulo m.
+    #[must_use]
+    pub fn int(&self, a: u128) -> ModInt {
+        ModInt { value: self.reduce(a), modulus: *self }
+    }
+}
+
+/// A residue modulo a [`Modulus`], with the arithmetic operators.
+///
+/// # Panics
+/// The operators panic on residues of different moduli.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ModInt {
+    value: u128,
+    modulus: Modulus,
+}
+
+impl ModInt {
+    /// The residue of a modulo m.
+    ///
+    /// # Panics
+    /// If m is 0.
+    #[must_use]
+    pub fn new(a: u128, m: u128) -> Self {
+        Modulus::new(m).int(a)
+    }
+    /// The residue as an integer in [0, m).
+    #[must_use]
+    pub fn value(&self) -> u128 {
+        self.value
+    }
+    #[must_use]
+    pub fn modulus(&self) -> Modulus {
+        self.modulus
+    }
+    #[must_use]
+    pub fn pow(self, exp: u128) -> Self {
+        self.with(self.modulus.pow(self.value, exp))
+    }
+    /// The multiplicative inverse, if the residue is coprime to m.
+    #[must_use]
+    pub fn inverse(self) -> Option<Self> {
+        self.modulus.inverse(self.value).map(|v| self.with(v))
+    }
+    fn with(self, value: u128) -> Self {
+        ModInt { value, ..self }
+    }
+    /// The value of a residue of the same modulus.
+    fn operand(&self, other: Self) -> u128 {
+        assert_eq!(self.modulus.m, other.modulus.m, "residues of different moduli");
+        other.value
+    }
+}
+
+impl Add for ModInt {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        self.with(add_mod(self.value, self.operand(rhs), self.modulus.m))
+    }
+}
+
+impl Sub for ModInt {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self.with(sub_mod(self.value, self.operand(rhs), self.modulus.m))
+    }
+}
+
+impl Mul for ModInt {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        self.with(self.modulus.mul(self.value, self.operand(rhs)))
+    }
+}
+
+impl Neg for ModInt {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self.with(sub_mod(0, self.value, self.modulus.m))
+    }
+}
+
+impl AddAssign for ModInt {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for ModInt {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign for ModInt {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl fmt::Display for ModInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (mod {})", self.value, self.modulus.m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        cipolla, crt, jacobi, legendre, mod_inverse, mod_mul, mod_pow, solve_linear_congruence,
+        sqrt_mod_prime, tonelli_shanks, u128_xgcd, ModInt, Modulus,
+    };
+
+    #[test]
+    fn test_xgcd() {
+        let cases = [(240, 46), (46, 240), (0, 7), (7, 0), (0, 0), (1, 1), (12, 12), (u128::MAX, u128::MAX - 1)];
+        for (a, b) in cases {
+            let (g, x, y) = u128_xgcd(a, b);
+            assert_eq!(g, crate::u128_gcd(a, b), "gcd({a}, {b})");
+            // a·x + b·y = g, checked modulo 2¹²⁸
+            let sum = a.wrapping_mul(x as u128).wrapping_add(b.wrapping_mul(y as u128));
+            assert_eq!(sum, g, "xgcd({a}, {b}) = ({g}, {x}, {y})");
+        }
+        // Coefficients near the edge of i128
+        let (a, b) = (u128::MAX, 1 << 127);
+        let (g, x, y) = u128_xgcd(a, b);
+        assert_eq!(g, 1);
+        assert!(x.unsigned_abs() <= b / 2 && y.unsigned_abs() <= a / 2);
+        assert_eq!(a.wrapping_mul(x as u128).wrapping_add(b.wrapping_mul(y as u128)), 1);
+    }
+
+    #[test]
+    fn test_mod_inverse() {
+        for m in [1u128, 2, 1_000_000_007, u64::MAX as u128, u128::MAX, u128::MAX - 158] {
+            for a in [1, 2, 3, 12345, m - 1, u128::MAX] {
+                if let Some(inv) = mod_inverse(a, m) {
+                    assert_eq!(mod_mul(a % m, inv, m), 1 % m, "{a}^-1 mod {m}");
+                } else {
+                    assert_ne!(crate::u128_gcd(a, m), 1);
+                }
+            }
+        }
+        assert_eq!(mod_inverse(6, 15), None);
+        assert_eq!(mod_inverse(3, 0), None);
+        assert_eq!(mod_inverse(0, 1), Some(0));
+    }
+
+    #[test]
+    fn test_modint() {
+        for m in [97u128, 1 << 64, u128::MAX - 158, u128::MAX] {
+            let modulus = Modulus::new(m);
+            let (a, b) = (modulus.int(u128::MAX - 5), modulus.int(1 << 100));
+            assert_eq!((a * b).value(), mod_mul((u128::MAX - 5) % m, (1 << 100) % m, m));
+            assert_eq!((a + b - b).value(), a.value());
+            assert_eq!((-a + a).value(), 0);
+            assert_eq!(a.pow(1000).value(), mod_pow(u128::MAX - 5, 1000, m));
+            if let Some(inv) = a.inverse() {
+                assert_eq!((a * inv).value(), 1);
+            }
+        }
+        let mut x = ModInt::new(3, 7);
+        x *= ModInt::new(5, 7);
+        x += ModInt::new(1, 7);
+        assert_eq!(x.to_string(), "2 (mod 7)");
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt(&[]), Some((0, 1)));
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        // Moduli with common factors, consistent and not
+        assert_eq!(crt(&[(3, 4), (5, 6)]), Some((11, 12)));
+        assert_eq!(crt(&[(3, 4), (4, 6)]), None);
+        assert_eq!(crt(&[(10, 4), (5, 12)]), None);
+        assert_eq!(crt(&[(14, 4), (2, 6), (8, 9)]), Some((26, 36)));
+        assert_eq!(crt(&[(1, 0)]), None);
+        // Moduli whose lcm is u128::MAX, and one past it
+        let (p, q) = (u128::MAX / 3, 3);
+        let (x, l) = crt(&[(p - 1, p), (2, q)]).unwrap();
+        assert_eq!((x % p, x % q, l), (p - 1, 2, u128::MAX));
+        assert_eq!(crt(&[(1, 1 << 127), (1, 3)]), None);
+        // Against a search over small moduli
+        for (m, n) in [(12, 18), (7, 9), (8, 20), (1, 5)] {
+            for (r, s) in [(0, 0), (1, 7), (4, 10), (5, 5)] {
+                let x = (0..m * n).find(|x| x % m == r % m && x % n == s % n);
+                assert_eq!(crt(&[(r, m), (s, n)]).map(|(x, _)| x), x, "({r}, {m}), ({s}, {n})");
+            }
+        }
+    }
+
+    #[test]
+    fn test_solve_linear_congruence() {
+        for m in 1..40u128 {
+            for a in 0..m + 3 {
+                for b in 0..m + 3 {
+                    let want: Vec<u128> = (0..m).filter(|x| (a * x) % m == b % m).collect();
+                    assert_eq!(solve_linear_congruence(a, b, m).collect::<Vec<_>>(), want, "{a}x = {b} mod {m}");
+                }
+            }
+        }
+        let big = solve_linear_congruence(0, 0, u128::MAX);
+        assert_eq!((big.step(), big.remaining()), (1, u128::MAX));
+        let solutions: Vec<u128> = solve_linear_congruence(1 << 126, 0, u128::MAX - 1).collect();
+        assert_eq!(solutions, [0, u128::MAX / 2]);
+        assert_eq!(solve_linear_congruence(3, 1, 0).count(), 0);
+    }
+
+    #[test]
+    fn test_jacobi_legendre() {
+        for n in (1..200u128).step_by(2) {
+            for a in 0..n {
+                let squares = (0..n).any(|x| x * x % n == a);
+                let j = jacobi(a, n);
+                if crate::u128_gcd(a, n) != 1 {
+                    assert_eq!(j, 0, "({a}/{n})");
+                } else if squares {
+                    assert_eq!(j, 1, "({a}/{n})");
+                }
+                if crate::u128_is_prime(n) {
+                    let expected = if a == 0 { 0 } else if squares { 1 } else { -1 };
+                    assert_eq!(legendre(a, n), expected, "({a}/{n})");
+                }
+            }
+        }
+        assert_eq!((legendre(3, 2), legendre(4, 2)), (1, 0));
+        // Euler's criterion for the largest u128 prime
+        let p = u128::MAX - 158;
+        for a in [2, 3, 5, 1 << 100, u128::MAX] {
+            let euler = mod_pow(a, p / 2, p);
+            assert_eq!(legendre(a, p), if euler == 1 { 1 } else { -1 }, "({a}/{p})");
+        }
+    }
+
+    #[test]
+    fn test_sqrt_mod_prime() {
+        // 3 mod 4, small and large 2-adic order, across the u128 range
+        let primes = [
+            2, 3, 5, 13, 17, 41, 65537, 1_000_000_007, 998_244_353, (1 << 64) - 59,
+            (15 << 27) + 1, (165 << 100) + 1, u128::MAX - 158, 170141183460469231731687303715884105727,
+        ];
+        for p in primes {
+            let modulus = Modulus::new(p);
+            for x in [0, 1, 2, 3, 12345, 1 << 63, u128::MAX] {
+                let sq = modulus.mul(x, x);
+                let r = sqrt_mod_prime(sq, p).unwrap();
+                assert_eq!(modulus.mul(r, r), sq, "sqrt({sq}) mod {p}");
+                assert!(r <= p - r);
+            }
+            if p > 2 {
+                let z = (2..p).find(|&z| legendre(z, p) == -1).unwrap();
+                assert_eq!(sqrt_mod_prime(z, p), None);
+            }
+        }
+        // Both methods agree where either could be used
+        for p in [(165 << 100) + 1, 998_244_353, 65537] {
+            let modulus = Modulus::new(p);
+            let s = (p - 1).trailing_zeros();
+            for a in [4, 5 * 5, 1 << 40] {
+                let (r1, r2) = (tonelli_shanks(a, &modulus, s).unwrap(), cipolla(a, &modulus).unwrap());
+                assert!(r1 == r2 || r1 == p - r2, "sqrt({a}) mod {p}");
+            }
+        }
+        // Composite and even moduli give a true root or None, without panics
+        for p in [4u128, 6, 9, 15, 25, 49, 100, 121, 561] {
+            for a in (0..200).chain([p - 1, u128::MAX]) {
+                let r = sqrt_mod_prime(a, p);
+                assert!(r.is_none_or(|r| mod_mul(r, r, p) == a % p), "sqrt({a}) mod {p}");
+                if p % 2 == 0 {
+                    assert_eq!(r, None, "sqrt({a}) mod {p}");
+                }
+            }
+        }
+        assert_eq!(sqrt_mod_prime(4, 9), None);
+        assert_eq!(sqrt_mod_prime(4, 25), None);
+        assert_eq!(sqrt_mod_prime(3, 65537 * 65537), None);
+        let (p, q) = ((1u128 << 61) - 1, (1u128 << 64) - 59);
+        assert!(sqrt_mod_prime(4, p * q).is_none_or(|r| mod_mul(r, r, p * q) == 4));
+    }
+}
@@ -0,0 +1,297 @@
+//! Montgomery modular arithmetic for odd moduli up to `u128::MAX`.
+//!
+//! A residue a is represented by a·R mod n with R = 2¹²⁸. In this form a
+//! modular product needs one full 128×128-bit multiplication and a
+//! Montgomery reduction, which replaces the division by n with two more
+//! multiplications and a shift. This avoids both the overflow of a plain
+//! `u128` product and the bit-by-bit fallback that `mod_mul` needs once
+//! the operands exceed 64 bits.
+//!
+//! Addition, subtraction and comparison with zero work on the Montgomery
+//! form unchanged, and since R is coprime to n, so does a gcd with n.
+//!
+//! Reference: <https://en.wikipedia.org/wiki/Montgomery_modular_multiplication>
+use crate::PrimeInt;
+
+/// The constants for arithmetic modulo a fixed odd n.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Montgomery {
+    n: u128,
+    /// −n⁻¹ mod R, so that n · n_neg_inv ≡ −1 (mod R).
+    n_neg_inv: u128,
+    /// R mod n: the Montgomery form of 1.
+    one: u128,
+    /// R² mod n, used to convert into Montgomery form.
+    r2: u128,
+}
+
+impl Montgomery {
+    /// Precompute the constants for the modulus `n`.
+    /// Returns `None` when `n` is even, since R = 2¹²⁸ must be invertible.
+    #[must_use]
+    pub fn new(n: u128) -> Option<Self> {
+        if n.is_multiple_of(2) {
+            return None;
+        }
+        // Newton's iteration doubles the number of correct low bits of the
+        // inverse each step, starting from 3 bits since n·n ≡ 1 (mod 8).
+        let mut inv = n;
+        for _ in 0..6 {
+            inv = inv.wrapping_mul(2u128.wrapping_sub(n.wrapping_mul(inv)));
+        }
+        debug_assert_eq!(n.wrapping_mul(inv), 1);
+        let one = (u128::MAX % n + 1) % n;
+        let mut mont = Self { n, n_neg_inv: inv.wrapping_neg(), one, r2: 0 };
+        // Squaring the Montgomery form of 2 seven times gives the Montgomery
+        // form of 2¹²⁸, which is R·R mod n.
+        let mut r2 = mont.add(one, one);
+        for _ in 0..7 {
+            r2 = mont.mul(r2, r2);
+        }
+        mont.r2 = r2;
+        Some(mont)
+    }
+    /// The modulus n.
+    #[must_use]
+    pub fn modulus(&self) -> u128 {
+        self.n
+    }
+    /// The Montgomery form of 1.
+    #[must_use]
+    pub fn one(&self) -> u128 {
+        self.one
+    }
+    /// Convert `a` into Montgomery form.
+    #[must_use]
+    pub fn to_montgomery(&self, a: u128) -> u128 {
+        self.mul(a % self.n, self.r2)
+    }
+    /// Convert `a` out of Montgomery form.
+    #[must_use]
+    pub fn from_montgomery(&self, a: u128) -> u128 {
+        self.reduce(0, a)
+    }
+    /// The Montgomery product a·b·R⁻¹ mod n of two values below n. On
+    /// Montgomery forms this is the Montgomery form of the product.
+    #[inline]
+    #[must_use]
+    pub fn mul(&self, a: u128, b: u128) -> u128 {
+        debug_assert!(a < self.n && b < self.n);
+        let (hi, lo) = mul_wide(a, b);
+        self.reduce(hi, lo)
+    }
+    /// Modular addition of two values below n.
+    #[inline]
+    #[must_use]
+    pub fn add(&self, a: u128, b: u128) -> u128 {
+        debug_assert!(a < self.n && b < self.n);
+        let (sum, carry) = a.overflowing_add(b);
+        if carry || sum >= self.n { sum.wrapping_sub(self.n) } else { sum }
+    }
+    /// Modular subtraction of two values below n.
+    #[inline]
+    #[must_use]
+    pub fn sub(&self, a: u128, b: u128) -> u128 {
+        debug_assert!(a < self.n && b < self.n);
+        if a >= b { a - b } else { a.wrapping_sub(b).wrapping_add(self.n) }
+    }
+    /// Raise `a`, in Montgomery form, to the power `exp`.
+    #[must_use]
+    pub fn pow(&self, mut a: u128, mut exp: u128) -> u128 {
+        let mut result = self.one;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = self.mul(result, a);
+            }
+            exp >>= 1;
+            if exp > 0 {
+                a = self.mul(a, a);
+            }
+        }
+        result
+    }
+    /// Modular exponentiation (base^exp) mod n on ordinary residues.
+    #[must_use]
+    pub fn mod_pow(&self, base: u128, exp: u128) -> u128 {
+        self.from_montgomery(self.pow(self.to_montgomery(base), exp))
+    }
+    /// Montgomery reduction of the 256-bit value hi·2¹²⁸ + lo < n·R,
+    /// returning (hi·2¹²⁸ + lo)·R⁻¹ mod n.
+    #[inline]
+    fn reduce(&self, hi: u128, lo: u128) -> u128 {
+        // m is chosen so that lo + m·n ≡ 0 (mod R), so the low half of the
+        // sum only contributes its carry.
+        let m = lo.wrapping_mul(self.n_neg_inv);
+        let (mn_hi, mn_lo) = mul_wide(m, self.n);
+        let carry = u128::from(lo.overflowing_add(mn_lo).1);
+        let (t, overflow) = hi.overflowing_add(mn_hi);
+        let (t, overflow2) = t.overflowing_add(carry);
+        if overflow || overflow2 || t >= self.n { t.wrapping_sub(self.n) } else { t }
+    }
+}
+
+/// The operations shared by the Montgomery types, so that algorithms such
+/// as Pollard's rho and the Baillie–PSW test can run on any of them.
+pub(crate) trait MontgomeryArith {
+    type Int: PrimeInt;
+    fn modulus(&self) -> Self::Int;
+    fn one(&self) -> Self::Int;
+    fn to_montgomery(&self, a: Self::Int) -> Self::Int;
+    fn mul(&self, a: Self::Int, b: Self::Int) -> Self::Int;
+    fn add(&self, a: Self::Int, b: Self::Int) -> Self::Int;
+    fn sub(&self, a: Self::Int, b: Self::Int) -> Self::Int;
+    fn pow(&self, a: Self::Int, exp: Self::Int) -> Self::Int;
+}
+
+impl MontgomeryArith for Montgomery {
+    type Int = u128;
+    fn modulus(&self) -> u128 {
+        self.n
+    }
+    fn one(&self) -> u128 {
+        self.one
+    }
+    fn to_montgomery(&self, a: u128) -> u128 {
+        Montgomery::to_montgomery(self, a)
+    }
+    #[inline]
+    fn mul(&self, a: u128, b: u128) -> u128 {
+        Montgomery::mul(self, a, b)
+    }
+    #[inline]
+    fn add(&self, a: u128, b: u128) -> u128 {
+        Montgomery::add(self, a, b)
+    }
+    #[inline]
+    fn sub(&self, a: u128, b: u128) -> u128 {
+        Montgomery::sub(self, a, b)
+    }
+    fn pow(&self, a: u128, exp: u128) -> u128 {
+        Montgomery::pow(self, a, exp)
+    }
+}
+
+/// The same arithmetic for odd moduli up to `u64::MAX`, with R = 2⁶⁴, where
+/// the double-width product is a native `u128`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Montgomery64 {
+    n: u64,
+    n_neg_inv: u64,
+    one: u64,
+    r2: u64,
+}
+
+impl Montgomery64 {
+    pub(crate) fn new(n: u64) -> Option<Self> {
+        if n.is_multiple_of(2) {
+            return None;
+        }
+        let mut inv = n;
+        for _ in 0..5 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(n.wrapping_mul(inv)));
+        }
+        debug_assert_eq!(n.wrapping_mul(inv), 1);
+        let n_wide = u128::from(n);
+        let one = ((1u128 << 64) % n_wide) as u64;
+        let r2 = (u128::from(one) * u128::from(one) % n_wide) as u64;
+        Some(Self { n, n_neg_inv: inv.wrapping_neg(), one, r2 })
+    }
+    pub(crate) fn one(&self) -> u64 {
+        self.one
+    }
+    pub(crate) fn to_montgomery(self, a: u64) -> u64 {
+        self.mul(a % self.n, self.r2)
+    }
+    #[inline]
+    pub(crate) fn mul(&self, a: u64, b: u64) -> u64 {
+        let t = u128::from(a) * u128::from(b);
+        let m = (t as u64).wrapping_mul(self.n_neg_inv);
+        let mn = u128::from(m) * u128::from(self.n);
+        let (sum, overflow) = t.overflowing_add(mn);
+        let r = (sum >> 64) as u64;
+        if overflow || r >= self.n { r.wrapping_sub(self.n) } else { r }
+    }
+    pub(crate) fn pow(&self, mut a: u64, mut exp: u64) -> u64 {
+        let mut result = self.one;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = self.mul(result, a);
+            }
+            exp >>= 1;
+            if exp > 0 {
+                a = self.mul(a, a);
+            }
+        }
+        result
+    }
+}
+
+/// The full 256-bit product of two `u128` values, as (high, low) halves.
+#[inline]
+pub(crate) fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a1, a0) = (a >> 64, a & MASK);
+    let (b1, b0) = (b >> 64, b & MASK);
+    let p00 = a0 * b0;
+    let p01 = a0 * b1;
+    let p10 = a1 * b0;
+    let p11 = a1 * b1;
+    let mid = (p00 >> 64) + (p01 & MASK) + (p10 & MASK);
+    let lo = (p00 & MASK) | (mid << 64);
+    let hi = p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64);
+    (hi, lo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mul_wide, Montgomery};
+    use crate::modular::{add_mod, mod_mul, sub_mod};
+
+    #[test]
+    fn test_mul_wide() {
+        assert_eq!(mul_wide(0, u128::MAX), (0, 0));
+        assert_eq!(mul_wide(1 << 64, 1 << 64), (1, 0));
+        assert_eq!(mul_wide(u128::MAX, u128::MAX), (u128::MAX - 1, 1));
+        assert_eq!(mul_wide(u64::MAX as u128, u64::MAX as u128), (0, (u64::MAX as u128).pow(2)));
+    }
+
+    #[test]
+    fn test_montgomery_matches_mod_mul() {
+        assert_eq!(Montgomery::new(10), None);
+        let moduli: [u128; 6] = [
+            3,
+            1000000007,
+            18446744073709551557,
+            (1 << 100) + 277,
+            u128::MAX - 158,
+            u128::MAX,
+        ];
+        for n in moduli {
+            let mont = Montgomery::new(n).unwrap();
+            let values = [0, 1, 2, n / 3, n / 2 + 1, n - 2, n - 1];
+            for a in values {
+                assert_eq!(mont.from_montgomery(mont.to_montgomery(a)), a);
+                for b in values {
+                    let (am, bm) = (mont.to_montgomery(a), mont.to_montgomery(b));
+                    let product = mont.from_montgomery(mont.mul(am, bm));
+                    assert_eq!(product, mod_mul(a, b, n), "{a} * {b} mod {n}");
+                    assert_eq!(mont.from_montgomery(mont.add(am, bm)), add_mod(a, b, n));
+                    assert_eq!(mont.from_montgomery(mont.sub(am, bm)), sub_mod(a, b, n));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_montgomery_pow() {
+        let p: u128 = (1 << 127) - 1;
+        let mont = Montgomery::new(p).unwrap();
+        // Fermat's little theorem for the Mersenne prime 2^127 - 1
+        for a in [2, 3, 12345678901234567890, p - 1] {
+            assert_eq!(mont.mod_pow(a, p - 1), 1);
+        }
+        assert_eq!(mont.mod_pow(3, 0), 1);
+        assert_eq!(Montgomery::new(1).unwrap().mod_pow(3, 5), 0);
+        assert_eq!(Montgomery::new(1000000007).unwrap().mod_pow(2, 30), 73741817);
+    }
+}
@@ -0,0 +1,300 @@
+//! Pollard's factoring methods for composites without small factors.
+//!
+//! Pollard's rho finds a factor p of n in roughly √p iterations of a
+//! pseudo-random polynomial map, independent of the size of n itself.
+//! This implementation uses Brent's cycle detection, which replaces Floyd's
+//! tortoise and hare with power-of-two checkpoints, and batches the gcd
+//! computations so that only one gcd is needed per block of iterations.
+//!
+//! Pollard's p−1 instead finds a prime factor p whenever p − 1 is smooth:
+//! stage 1 raises a base to every prime power up to B1, so that by Fermat's
+//! little theorem the result is 1 modulo p, and stage 2 allows for one
+//! further prime factor of p − 1 in (B1, B2].
+//!
+//! Reference: <https://en.wikipedia.org/wiki/Pollard%27s_rho_algorithm>
+//! Reference: <https://en.wikipedia.org/wiki/Pollard%27s_p_%E2%88%92_1_algorithm>
+use crate::modular::sub_mod;
+use crate::montgomery::{Montgomery, MontgomeryArith};
+use crate::sieve::SegmentedSieve;
+use crate::{u128_gcd, u128_is_prime, PrimeInt};
+
+/// The number of iterations whose differences are multiplied together
+/// before a single gcd is computed.
+const BATCH_SIZE: u128 = 128;
+
+/// Find a non-trivial factor of `n` using Pollard's rho with Brent's
+/// cycle detection.
+///
+/// Returns `None` when `n` has no non-trivial factor, i.e. when `n` is 0, 1
+/// or a prime. The returned factor is not necessarily prime.
+#[must_use]
+pub fn pollard_rho(n: u128) -> Option<u128> {
+    if n < 4 || u128_is_prime(n) {
+        return None;
+    }
+    Some(find_factor(n))
+}
+
+/// Find a non-trivial factor of the composite `n`, retrying with new
+/// polynomial constants until a split is found.
+pub(crate) fn find_factor(n: u128) -> u128 {
+    debug_assert!(n >= 4);
+    if n.is_multiple_of(2) {
+        return 2;
+    }
+    let mont = Montgomery::new(n).expect("n is odd");
+    find_factor_mont(&mont)
+}
+
+/// The search behind [`find_factor`], for the odd composite modulus of any
+/// of the Montgomery types.
+pub(crate) fn find_factor_mont<M: MontgomeryArith>(mont: &M) -> M::Int {
+    let mut c = M::Int::ONE;
+    loop {
+        if let Some(d) = brent(mont, M::Int::from(2), c, u128::MAX) {
+            return d;
+        }
+        c += M::Int::ONE;
+    }
+}
+
+/// Like [`find_factor`], but gives up on each of a few polynomials after
+/// roughly `max_iterations` steps, so that a factor that rho would take too
+/// long to find can be left to a method whose cost does not depend on it.
+pub(crate) fn find_factor_within(n: u128, max_iterations: u128) -> Option<u128> {
+    debug_assert!(n >= 4);
+    if n.is_multiple_of(2) {
+        return Some(2);
+    }
+    let mont = Montgomery::new(n)?;
+    find_factor_mont_within(&mont, max_iterations)
+}
+
+/// The search behind [`find_factor_within`], for the odd composite modulus
+/// of any of the Montgomery types.
+pub(crate) fn find_factor_mont_within<M: MontgomeryArith>(mont: &M, max_iterations: u128) -> Option<M::Int> {
+    (1..=3u8).find_map(|c| brent(mont, M::Int::from(2), M::Int::from(c), max_iterations))
+}
+
+/// Run a single Brent cycle search on f(x) = x² + c (mod n) starting at `x0`.
+/// Returns `None` when the cycle closes without exposing a proper factor,
+/// or when the search passes `limit` iterations.
+fn brent<M: MontgomeryArith>(mont: &M, x0: M::Int, c: M::Int, limit: u128) -> Option<M::Int> {
+    // The map runs on Montgomery forms. This changes the constant c, but
+    // the sequence is just as pseudo-random, and since the differences are
+    // only ever fed into a gcd with n, they never need converting back.
+    let n = mont.modulus();
+    let one = M::Int::ONE;
+    let c = mont.to_montgomery(c);
+    let f = |x| mont.add(mont.mul(x, x), c);
+    let abs_diff = |a: M::Int, b: M::Int| if a > b { a - b } else { b - a };
+    let mut y = mont.to_montgomery(x0);
+    let mut x = y;
+    let mut ys = y;
+    let mut q = mont.one();
+    let mut g = one;
+    let mut r: u128 = 1;
+    while g == one {
+        if r > limit {
+            return None;
+        }
+        x = y;
+        for _ in 0..r {
+            y = f(y);
+        }
+        let mut k = 0;
+        while k < r && g == one {
+            ys = y;
+            for _ in 0..BATCH_SIZE.min(r - k) {
+                y = f(y);
+                q = mont.mul(q, abs_diff(x, y));
+            }
+            g = q.gcd(n);
+            k += BATCH_SIZE;
+        }
+        r *= 2;
+    }
+    if g == n {
+        // The batched product hit zero; step through the last batch one
+        // iteration at a time to recover the factor it contained.
+        loop {
+            ys = f(ys);
+            g = abs_diff(x, ys).gcd(n);
+            if g > one {
+                break;
+            }
+        }
+    }
+    if g == n { None } else { Some(g) }
+}
+
+/// Find a non-trivial factor of `n` using Pollard's p−1 method with
+/// smoothness bounds `b1` for stage 1 and `b2` for stage 2.
+///
+/// Succeeds when some prime factor p of `n` has p − 1 composed of prime
+/// powers up to `b1` and at most one further prime up to `b2`. Returns
+/// `None` when `n` is 0, 1 or a prime, or when no factor was found within
+/// the bounds. The returned factor is not necessarily prime.
+#[must_use]
+pub fn pollard_pm1(n: u128, b1: u64, b2: u64) -> Option<u128> {
+    if n < 4 || u128_is_prime(n) {
+        return None;
+    }
+    find_factor_pm1(n, b1, b2)
+}
+
+/// Run both stages of p−1 on the composite `n`.
+pub(crate) fn find_factor_pm1(n: u128, b1: u64, b2: u64) -> Option<u128> {
+    debug_assert!(n >= 4);
+    if n.is_multiple_of(2) {
+        return Some(2);
+    }
+    let mont = Montgomery::new(n)?;
+    let base = mont.to_montgomery(2);
+    let a = match stage1(n, b1, base, mont.one(), |a, e| mont.pow(a, e)) {
+        Ok(a) => a,
+        Err(g) => return (g < n).then_some(g),
+    };
+    pm1_stage2(&mont, a, b1, b2)
+}
+
+/// The stage 1 shared by p−1 and p+1: starting from `x`, apply `pow` with
+/// the largest power of every prime up to `b1` as exponent. Returns the
+/// final value when gcd(x − `unit`, n) is still 1, and the gcd as the error
+/// otherwise. The gcd is checked once per batch of primes; when a batch
+/// takes every prime factor of n at once, it is replayed one prime at a
+/// time to separate them, which relies on pow(pow(x, a), b) = pow(x, ab).
+pub(crate) fn stage1(
+    n: u128,
+    b1: u64,
+    mut x: u128,
+    unit: u128,
+    pow: impl Fn(u128, u128) -> u128,
+) -> Result<u128, u128> {
+    let bound = u128::from(b1);
+    let mut primes = SegmentedSieve::new(2..b1.saturating_add(1)).map(u128::from).peekable();
+    while primes.peek().is_some() {
+        let batch: Vec<u128> = primes.by_ref().take(BATCH_SIZE as usize).collect();
+        let checkpoint = x;
+        for &p in &batch {
+            x = pow(x, largest_power(p, bound));
+        }
+        let g = u128_gcd(sub_mod(x, unit, n), n);
+        if g == 1 {
+            continue;
+        }
+        if g < n {
+            return Err(g);
+        }
+        x = checkpoint;
+        for &p in &batch {
+            for _ in 0..largest_power(p, bound).ilog(p) {
+                x = pow(x, p);
+                let g = u128_gcd(sub_mod(x, unit, n), n);
+                if g > 1 {
+                    return Err(g);
+                }
+            }
+        }
+    }
+    Ok(x)
+}
+
+/// The largest power of the prime `p` not exceeding `bound`.
+fn largest_power(p: u128, bound: u128) -> u128 {
+    let mut pk = p;
+    while pk <= bound / p {
+        pk *= p;
+    }
+    pk
+}
+
+/// Stage 2 of p−1: walk the primes q in (b1, b2], stepping from a^q to the
+/// next a^q' with a cached power a^(q' − q) for each prime gap, and
+/// multiply the values a^q − 1 together for a batched gcd. All values are
+/// in Montgomery form.
+fn pm1_stage2(mont: &Montgomery, a: u128, b1: u64, b2: u64) -> Option<u128> {
+    let n = mont.modulus();
+    let one = mont.one();
+    let mut primes = SegmentedSieve::new(b1.max(2).saturating_add(1)..b2.saturating_add(1)).map(u128::from);
+    let mut prev = primes.next()?;
+    let mut aq = mont.pow(a, prev);
+    let mut acc = mont.sub(aq, one);
+    // gap_powers[i] = a^(2i + 2), filled in as larger gaps are met.
+    let mut gap_powers = vec![mont.mul(a, a)];
+    for (i, q) in primes.enumerate() {
+        let idx = ((q - prev) / 2 - 1) as usize;
+        while gap_powers.len() <= idx {
+            let next = mont.mul(gap_powers[gap_powers.len() - 1], gap_powers[0]);
+            gap_powers.push(next);
+        }
+        aq = mont.mul(aq, gap_powers[idx]);
+        acc = mont.mul(acc, mont.sub(aq, one));
+        prev = q;
+        if (i as u128 + 1).is_multiple_of(BATCH_SIZE) && u128_gcd(acc, n) > 1 {
+            break;
+        }
+    }
+    let g = u128_gcd(acc, n);
+    (g > 1 && g < n).then_some(g)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_factor, find_factor_within, pollard_pm1, pollard_rho};
+
+    #[test]
+    fn test_pollard_rho_semiprimes() {
+        let cases: [(u128, u128); 4] = [
+            (65521, 65537),
+            (1000003, 1000033),
+            (4294967279, 4294967291),
+            (1000000007, 998244353),
+        ];
+        for (p, q) in cases {
+            let d = pollard_rho(p * q).unwrap();
+            assert!(d == p || d == q, "{d} is not a factor of {p} * {q}");
+        }
+    }
+
+    #[test]
+    fn test_find_factor_within_gives_up() {
+        let (p, q): (u128, u128) = (1000000007, 2305843009213693951);
+        assert_eq!(find_factor_within(p * q, 16), None);
+        assert_eq!(find_factor_within(p * q, 1 << 20), Some(p));
+    }
+
+    #[test]
+    fn test_find_factor_prime_powers() {
+        let p: u128 = 1000003;
+        for e in 2..=4 {
+            let n = p.pow(e);
+            let d = find_factor(n);
+            assert!(d > 1 && d < n && n.is_multiple_of(d));
+        }
+    }
+
+    #[test]
+    fn test_pollard_pm1_prime_powers() {
+        // p - 1 is smooth, so p shows up in every power of p
+        let p: u128 = 1116980824997;
+        assert_eq!(pollard_pm1(p * p, 1_000, 1_000), Some(p));
+        assert_eq!(pollard_pm1(p * p * p, 1_000, 1_000), Some(p));
+        // q - 1 is not, and the extra factor q of the group order hides q
+        let q: u128 = 18446744073709551557;
+        assert_eq!(pollard_pm1(q * q, 1_000, 50_000), None);
+    }
+
+    #[test]
+    fn test_pollard_pm1_smooth_factors() {
+        // 2^64 - 59 has p - 1 = 2^2 * 11 * 137 * 547 * 5594472617641
+        let q: u128 = 18446744073709551557;
+        // p - 1 = 2^2 * 31 * 41 * 47 * 137 * 149 * 229, found in stage 1
+        let p: u128 = 1116980824997;
+        assert_eq!(pollard_pm1(p * q, 1_000, 1_000), Some(p));
+        // p - 1 = 2 * 19 * 43 * 157 * 229 * 257 * 40009, needs stage 2
+        let p: u128 = 604057118838227;
+        assert_eq!(pollard_pm1(p * q, 1_000, 1_000), None);
+        assert_eq!(pollard_pm1(p * q, 1_000, 50_000), Some(p));
+    }
+}
@@ -0,0 +1,345 @@
+//! The unsigned integer types the library works on.
+//!
+//! [`PrimeInt`] is implemented for `u32`, `u64`, `u128` and [`crate::U256`],
+//! and the prime iterators and factorizations are generic over it, so that
+//! trial division and primality tests on narrower types run at their native
+//! width. Each type has its own deterministic Miller-Rabin test: bases
+//! {2, 7, 61} for `u32`, a seven-base set in 64-bit Montgomery form for
+//! `u64`, and the 128-bit test of [`crate::u128_is_prime`] for `u128`;
+//! `U256` values above `u128` take the Baillie–PSW test and Miller-Rabin to
+//! the first twelve prime bases. The methods for
+//! splitting large cofactors (SQUFOF, rho, SIQS and the rest) work on
+//! `u128`, and narrower cofactors are widened for them; wider ones are split
+//! with rho until they fit. Since that can fail, `factorize` needs the
+//! [`Factorable`] subtrait, which `U256` does not implement.
+//!
+//! The trait is sealed: its methods assume the arithmetic of the unsigned
+//! types above, so it cannot be implemented outside this crate.
+//!
+//! Reference: <https://miller-rabin.appspot.com/>
+use std::fmt;
+use std::hash::Hash;
+use std::ops::{
+    Add, AddAssign, BitAnd, Div, DivAssign, Mul, MulAssign, Rem, RemAssign, Shr, ShrAssign, Sub,
+    SubAssign,
+};
+use crate::candidates::miller_rabin_witness;
+use crate::montgomery::Montgomery64;
+use crate::{small_prime_test, u128_is_prime, Factorization, Primes, PrimesDescending};
+
+pub(crate) mod sealed {
+    use crate::u256::{self, U256};
+
+    pub trait Sealed: Sized {
+        /// Find a proper factor of a composite that does not fit in
+        /// `u128`, or `None` if it is out of reach.
+        fn split_wide(self) -> Option<Self> {
+            unreachable!("every value fits in u128")
+        }
+    }
+    impl Sealed for u32 {}
+    impl Sealed for u64 {}
+    impl Sealed for u128 {}
+    impl Sealed for U256 {
+        fn split_wide(self) -> Option<Self> {
+            u256::find_factor(self)
+        }
+    }
+}
+
+/// An unsigned integer type that primes can be found and factored in.
+pub trait PrimeInt:
+    sealed::Sealed
+    + Copy
+    + Default
+    + Eq
+    + Ord
+    + Hash
+    + fmt::Debug
+    + fmt::Display
+    + Send
+    + Sync
+    + 'static
+    + From<u8>
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+    + BitAnd<Output = Self>
+    + Shr<u32, Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+    + RemAssign
+    + ShrAssign<u32>
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const MAX: Self;
+    const BITS: u32;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn pow(self, exp: u32) -> Self;
+    fn trailing_zeros(self) -> u32;
+    fn leading_zeros(self) -> u32;
+    fn is_multiple_of(self, rhs: Self) -> bool;
+    fn isqrt(self) -> Self;
+
+    /// The value as a `u128`, if it fits.
+    #[must_use]
+    fn to_u128(self) -> Option<u128>;
+
+    /// Convert a `u128` known to fit, such as a divisor of a value of this
+    /// type.
+    ///
+    /// # Panics
+    /// If the value does not fit the type.
+    #[must_use]
+    fn from_u128(n: u128) -> Self;
+
+    /// Test if the value is a prime number, with a deterministic test at
+    /// the native width. For `u128` this is [`crate::u128_is_prime`]. For
+    /// [`crate::U256`] values above `u128` it is a probable prime test;
+    /// see [`crate::U256::is_prime`].
+    #[must_use]
+    fn is_prime(self) -> bool;
+
+    /// The smallest prime >= self, or `None` if it does not fit the type.
+    #[must_use]
+    fn next_prime(self) -> Option<Self> {
+        Primes::from(self).next()
+    }
+
+    /// The largest prime <= self, or `None` if self is below 2.
+    #[must_use]
+    fn prev_prime(self) -> Option<Self> {
+        PrimesDescending::from(self).next()
+    }
+
+    /// The greatest common divisor, by Euclid's algorithm.
+    #[must_use]
+    fn gcd(self, other: Self) -> Self {
+        let (mut a, mut b) = (self, other);
+        while b > Self::ZERO {
+            (a, b) = (b, a % b);
+        }
+        a
+    }
+
+    /// The least common multiple, or `None` on overflow.
+    #[must_use]
+    fn checked_lcm(self, other: Self) -> Option<Self> {
+        if self == Self::ZERO || other == Self::ZERO {
+            return Some(Self::ZERO);
+        }
+        (self / self.gcd(other)).checked_mul(other)
+    }
+
+    /// The least common multiple.
+    ///
+    /// # Panics
+    /// If the result does not fit the type.
+    #[must_use]
+    fn lcm(self, other: Self) -> Self {
+        self.checked_lcm(other).expect("lcm overflow")
+    }
+
+    /// The prime factorization of the value. Only for the [`Factorable`]
+    /// types; a [`crate::U256`] is factored with
+    /// [`PrimeInt::try_factorize`].
+    #[must_use]
+    fn factorize(self) -> Factorization<Self>
+    where
+        Self: Factorable,
+    {
+        Factorization::factorize(self)
+    }
+
+    /// The prime factorization of the value, or `None` if it is out of
+    /// reach; see [`Factorization::try_factorize`].
+    #[must_use]
+    fn try_factorize(self) -> Option<Factorization<Self>> {
+        Factorization::try_factorize(self)
+    }
+}
+
+/// The [`PrimeInt`] types whose every value can be factored: `u32`, `u64`
+/// and `u128`. [`crate::U256`] is not one of them, since a cofactor above
+/// `u128` with two large prime factors is out of reach.
+pub trait Factorable: PrimeInt {}
+
+impl Factorable for u32 {}
+impl Factorable for u64 {}
+impl Factorable for u128 {}
+
+macro_rules! prime_int_common {
+    ($t:ty) => {
+        const ZERO: Self = 0;
+        const ONE: Self = 1;
+        const MAX: Self = <$t>::MAX;
+        const BITS: u32 = <$t>::BITS;
+
+        #[inline]
+        fn checked_add(self, rhs: Self) -> Option<Self> {
+            <$t>::checked_add(self, rhs)
+        }
+        #[inline]
+        fn checked_mul(self, rhs: Self) -> Option<Self> {
+            <$t>::checked_mul(self, rhs)
+        }
+        #[inline]
+        fn pow(self, exp: u32) -> Self {
+            <$t>::pow(self, exp)
+        }
+        #[inline]
+        fn trailing_zeros(self) -> u32 {
+            <$t>::trailing_zeros(self)
+        }
+        #[inline]
+        fn leading_zeros(self) -> u32 {
+            <$t>::leading_zeros(self)
+        }
+        #[inline]
+        fn is_multiple_of(self, rhs: Self) -> bool {
+            <$t>::is_multiple_of(self, rhs)
+        }
+        fn isqrt(self) -> Self {
+            <$t>::isqrt(self)
+        }
+        #[inline]
+        fn to_u128(self) -> Option<u128> {
+            Some(self.into())
+        }
+        #[inline]
+        fn from_u128(n: u128) -> Self {
+            Self::try_from(n).expect("value does not fit")
+        }
+    };
+}
+
+impl PrimeInt for u32 {
+    prime_int_common!(u32);
+
+    fn is_prime(self) -> bool {
+        // Deterministic for all n < 4,759,123,141
+        const WITNESSES: [u64; 3] = [2, 7, 61];
+        if let Some(prime) = small_prime_test(self) {
+            return prime;
+        }
+        let n = u64::from(self);
+        let mul = |a: u64, b: u64| a * b % n;
+        let pow = |mut a: u64, mut e: u64| {
+            let mut r = 1;
+            while e > 0 {
+                if e & 1 == 1 { r = mul(r, a); }
+                a = mul(a, a);
+                e >>= 1;
+            }
+            r
+        };
+        let r = (n - 1).trailing_zeros();
+        let d = (n - 1) >> r;
+        WITNESSES.iter().all(|&a| miller_rabin_witness(pow(a, d), 1, n - 1, r, |x| mul(x, x)))
+    }
+}
+
+impl PrimeInt for u64 {
+    prime_int_common!(u64);
+
+    fn is_prime(self) -> bool {
+        // Deterministic for all n < 2^64
+        const WITNESSES: [u64; 7] = [2, 325, 9375, 28178, 450775, 9780504, 1795265022];
+        if let Some(prime) = small_prime_test(self) {
+            return prime;
+        }
+        let Some(mont) = Montgomery64::new(self) else {
+            return false;
+        };
+        let r = (self - 1).trailing_zeros();
+        let d = (self - 1) >> r;
+        let one = mont.one();
+        let minus_one = mont.to_montgomery(self - 1);
+        WITNESSES.iter().all(|&a| {
+            let a = a % self;
+            a == 0 || {
+                let x = mont.pow(mont.to_montgomery(a), d);
+                miller_rabin_witness(x, one, minus_one, r, |x| mont.mul(x, x))
+            }
+        })
+    }
+}
+
+impl PrimeInt for u128 {
+    prime_int_common!(u128);
+
+    fn is_prime(self) -> bool {
+        u128_is_prime(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrimeInt;
+    use crate::{u128_is_prime, Factorization};
+
+    #[test]
+    fn test_is_prime_matches_u128() {
+        for n in 0..200_000u32 {
+            assert_eq!(n.is_prime(), u128_is_prime(n.into()), "{n}");
+            assert_eq!(u64::from(n).is_prime(), u128_is_prime(n.into()), "{n}");
+        }
+        let edge_cases: [u64; 8] = [
+            4294967291,
+            4294967295,
+            4759123141, // strong pseudoprime to bases 2, 7 and 61
+            3215031751,
+            18446744073709551557,
+            18446744073709551559,
+            u64::MAX,
+            4294967291 * 4294967279,
+        ];
+        for n in edge_cases {
+            assert_eq!(n.is_prime(), u128_is_prime(n.into()), "{n}");
+            if let Ok(m) = u32::try_from(n) {
+                assert_eq!(m.is_prime(), u128_is_prime(n.into()), "{n}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_factorize_native_widths() {
+        for n in [0u32, 1, 2, 12, 65535, 4294967291, 4294967295, 999_999_999] {
+            let expected = Factorization::factorize(u128::from(n)).to_vec();
+            let got: Vec<u128> = n.factorize().to_vec().into_iter().map(u128::from).collect();
+            assert_eq!(got, expected, "factorize({n}u32)");
+        }
+        let cases: [u64; 5] = [
+            600851475143,
+            4294967291 * 4294967279,
+            1000003 * 1000033 * 1000037,
+            18446744073709551557,
+            u64::MAX,
+        ];
+        for n in cases {
+            let expected = Factorization::factorize(u128::from(n)).to_string();
+            assert_eq!(n.factorize().to_string(), expected, "factorize({n}u64)");
+            assert_eq!(n.factorize().value(), n);
+        }
+    }
+
+    #[test]
+    fn test_primes_at_type_bounds() {
+        assert_eq!(4294967290u32.next_prime(), Some(4294967291));
+        assert_eq!(4294967292u32.next_prime(), None);
+        assert_eq!(u32::MAX.prev_prime(), Some(4294967291));
+        assert_eq!(u64::MAX.prev_prime(), Some(18446744073709551557));
+        assert_eq!(18446744073709551558u64.next_prime(), None);
+        assert_eq!(1u64.prev_prime(), None);
+        assert_eq!(12u32.gcd(18), 6);
+        assert_eq!(12u64.lcm(18), 36);
+        assert_eq!(u32::MAX.checked_lcm(2), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Serde support for factorizations, the prime wheel and the prime
+//! iterators, behind the `serde` feature.
+//!
+//! Integers are written as decimal strings in human-readable formats such
+//! as JSON, where numbers beyond 2⁵³ lose precision, and natively in binary
+//! formats such as bincode (as strings for `U256`). Deserialization checks
+//! the data as parsing does: factors must be prime, in strictly ascending
+//! order and with a product that fits the type, and a wheel must be in a
+//! state it can reach. The prime iterators are stored as the position to
+//! resume from, which [`Primes`] pairs with its sieve ceiling.
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+use crate::candidates::Wheel210;
+use crate::{parse_decimal, Factor, Factorization, PrimeInt, Primes, PrimesDescending};
+
+/// An integer of any [`PrimeInt`] type, in the representation above.
+struct Int<T>(T);
+
+impl<T: PrimeInt> Serialize for Int<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.to_u128() {
+            Some(n) if T::BITS <= 128 && !serializer.is_human_readable() => {
+                serializer.serialize_u128(n)
+            }
+            _ => serializer.collect_str(&self.0),
+        }
+    }
+}
+
+impl<'de, T: PrimeInt> Deserialize<'de> for Int<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(IntVisitor(PhantomData))
+        } else if T::BITS <= 128 {
+            deserializer.deserialize_u128(IntVisitor(PhantomData))
+        } else {
+            deserializer.deserialize_str(IntVisitor(PhantomData))
+        }
+    }
+}
+
+struct IntVisitor<T>(PhantomData<T>);
+
+impl<T: PrimeInt> Visitor<'_> for IntVisitor<T> {
+    type Value = Int<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an unsigned integer of at most {} bits", T::BITS)
+    }
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+        parse_decimal(s).map(Int).ok_or_else(|| E::invalid_value(de::Unexpected::Str(s), &self))
+    }
+    fn visit_u64<E: de::Error>(self, n: u64) -> Result<Self::Value, E> {
+        self.visit_u128(n.into())
+    }
+    fn visit_u128<E: de::Error>(self, n: u128) -> Result<Self::Value, E> {
+        if T::MAX.to_u128().is_some_and(|max| n > max) {
+            return Err(E::invalid_value(de::Unexpected::Other("an integer too large"), &self));
+        }
+        Ok(Int(T::from_u128(n)))
+    }
+}
+
+/// The serialized form of a [`Factor`].
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Factor", bound = "T: PrimeInt")]
+struct FactorRepr<T> {
+    integer: Int<T>,
+    exponent: u32,
+}
+
+impl<T: PrimeInt> Serialize for Factor<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        FactorRepr { integer: Int(self.integer), exponent: self.exponent }.serialize(serializer)
+    }
+}
+
+impl<'de, T: PrimeInt> Deserialize<'de> for Factor<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let FactorRepr { integer, exponent } = FactorRepr::deserialize(deserializer)?;
+        Factor::checked(integer.0, exponent).map_err(de::Error::custom)
+    }
+}
+
+/// A factorization is the sequence of its factors.
+impl<T: PrimeInt> Serialize for Factorization<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.factors)
+    }
+}
+
+impl<'de, T: PrimeInt> Deserialize<'de> for Factorization<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let factors = Vec::deserialize(deserializer)?;
+        Factorization::checked(factors).map_err(de::Error::custom)
+    }
+}
+
+/// The serialized form of a [`Wheel210`]: the last candidate and the index
+/// of the gap to the next.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Wheel210", bound = "T: PrimeInt")]
+struct WheelRepr<T> {
+    base: Int<T>,
+    index: usize,
+}
+
+impl<T: PrimeInt> Serialize for Wheel210<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        WheelRepr { base: Int(self.base), index: self.index }.serialize(serializer)
+    }
+}
+
+impl<'de, T: PrimeInt> Deserialize<'de> for Wheel210<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let WheelRepr { base, index } = WheelRepr::deserialize(deserializer)?;
+        let wheel = Wheel210 { base: base.0, index };
+        if !wheel.is_valid() {
+            return Err(de::Error::custom(format!("{} is not at wheel index {index}", base.0)));
+        }
+        Ok(wheel)
+    }
+}
+
+/// The serialized form of a [`Primes`]: every prime below `next` has been
+/// yielded, and none above it.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Primes", bound = "T: PrimeInt")]
+struct PrimesRepr<T> {
+    next: Int<T>,
+    sieve_ceiling: u64,
+}
+
+impl<T: PrimeInt> Serialize for Primes<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let next = match &self.sieve {
+            Some(sieve) => T::from_u128(sieve.position().into()),
+            // The wheel is on the last candidate it passed
+            None => self.wheel.base.checked_add(T::ONE).unwrap_or(T::MAX),
+        };
+        PrimesRepr { next: Int(next), sieve_ceiling: self.ceiling }.serialize(serializer)
+    }
+}
+
+impl<'de, T: PrimeInt> Deserialize<'de> for Primes<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let PrimesRepr { next, sieve_ceiling } = PrimesRepr::deserialize(deserializer)?;
+        Ok(Primes::from(next.0).sieve_ceiling(sieve_ceiling))
+    }
+}
+
+/// The serialized form of a [`PrimesDescending`]: every prime above
+/// `next` has been yielded, and none at or below it.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "PrimesDescending", bound = "T: PrimeInt")]
+struct PrimesDescendingRepr<T> {
+    next: Int<T>,
+}
+
+impl<T: PrimeInt> Serialize for PrimesDescending<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // The wheel's base is the next candidate it goes back to
+        PrimesDescendingRepr { next: Int(self.wheel.base) }.serialize(serializer)
+    }
+}
+
+impl<'de, T: PrimeInt> Deserialize<'de> for PrimesDescending<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let PrimesDescendingRepr { next } = PrimesDescendingRepr::deserialize(deserializer)?;
+        Ok(PrimesDescending::from(next.0))
+    }
+}
@@ -0,0 +1,401 @@
+//! A segmented sieve of Eratosthenes for enumerating dense ranges of primes.
+//!
+//! The range is sieved one segment at a time, with a bitmap of the odd
+//! values in the segment that fits in the L2 cache. Each odd prime up to
+//! the square root of the end of the range crosses off its multiples in
+//! every segment, and remembers where it stopped, so the cost is a few bit
+//! operations per value instead of a primality test per candidate. The
+//! primes up to the square root come from a sieve of their own, so memory
+//! stays at O(√n) however long the range is. A range much narrower than
+//! √n is not worth that: its base primes stop at the width of the range,
+//! and the few values left after sieving are tested for primality, so
+//! that the last thousand values below 2⁶⁴ do not cost 2³² base primes.
+//!
+//! [`FactorSieve`] sieves a range the same way to factor every value in
+//! it: each prime divides out of its multiples in a segment of cofactors,
+//! so a value costs a division per prime factor rather than a trial
+//! division per candidate.
+//!
+//! Reference: <https://en.wikipedia.org/wiki/Sieve_of_Eratosthenes#Segmented_sieve>
+use std::ops::Range;
+
+use crate::{PrimeFactors, PrimeInt};
+
+/// The number of odd values in a full segment: a 256 KiB bitmap covering
+/// 2²² consecutive integers.
+const SEGMENT_BITS: u64 = 1 << 21;
+
+/// The base primes a [`SegmentedSieve`] uses at least, however narrow its
+/// range: enough that only about one odd value in twelve is left to test.
+const MIN_BASE_LIMIT: u64 = 1 << 16;
+
+/// The number of values in a segment of the [`FactorSieve`].
+const FACTOR_SEGMENT: u64 = 1 << 16;
+
+/// The largest prime the [`FactorSieve`] divides out. Values below its
+/// square, 2⁴⁰, are factored by the sieve alone; any larger cofactor left
+/// is factored on its own.
+const FACTOR_SIEVE_BOUND: u64 = 1 << 20;
+
+/// Return all primes <= `n` in ascending order.
+#[must_use]
+pub fn primes_up_to(n: u64) -> Vec<u64> {
+    primes_in_range(0..n.saturating_add(1))
+}
+
+/// Return all primes in the half-open range in ascending order.
+#[must_use]
+pub fn primes_in_range(range: Range<u64>) -> Vec<u64> {
+    SegmentedSieve::new(range).collect()
+}
+
+/// An iterator over the primes in a range, sieved one segment at a time.
+#[derive(Clone, Debug)]
+pub struct SegmentedSieve {
+    /// The odd primes up to √end, or up to the width of a narrow range,
+    /// with the next odd multiple of each to cross off, at or after the
+    /// current segment.
+    base: Vec<(u64, u64)>,
+    /// Whether the base primes stop short of √end, so that the values left
+    /// after sieving must be tested for primality.
+    test: bool,
+    /// The exclusive end of the range.
+    end: u64,
+    /// The odd value of bit 0 in the current segment.
+    low: u64,
+    /// Bit i is set when low + 2i is a prime in the range.
+    bits: Vec<u64>,
+    /// The index of the word being scanned and its remaining bits.
+    word: usize,
+    current: u64,
+    /// Whether 2 is in the range and not yet yielded.
+    two: bool,
+}
+
+impl SegmentedSieve {
+    /// Create an iterator over the primes in the half-open range.
+    #[must_use]
+    pub fn new(range: Range<u64>) -> Self {
+        let Range { start, end } = range;
+        // The largest value to test is end − 1, which needs the primes up
+        // to its square root.
+        let root = end.saturating_sub(1).isqrt();
+        let limit = root.min(end.saturating_sub(start).max(MIN_BASE_LIMIT));
+        let base = if limit < 3 {
+            Vec::new()
+        } else {
+            SegmentedSieve::new(3..limit + 1).map(|p| (p, p * p)).collect()
+        };
+        let low = start.max(1) | 1;
+        let mut sieve = SegmentedSieve {
+            base,
+            test: limit < root,
+            end,
+            low,
+            bits: Vec::new(),
+            word: 0,
+            current: 0,
+            two: start <= 2 && 2 < end,
+        };
+        if low < end {
+            sieve.sieve_segment();
+        }
+        sieve
+    }
+
+    /// Cross off the composites in the segment that starts at `low`.
+    fn sieve_segment(&mut self) {
+        let len = SEGMENT_BITS.min((self.end - self.low).div_ceil(2));
+        let words = len.div_ceil(64) as usize;
+        self.bits.clear();
+        self.bits.resize(words, u64::MAX);
+        // Clear the bits past the end of the range, and the value 1
+        if !len.is_multiple_of(64) {
+            self.bits[words - 1] = (1 << (len % 64)) - 1;
+        }
+        if self.low == 1 {
+            self.bits[0] &= !1;
+        }
+        // Primes below 64 hit every word, so whole-word masks repeating
+        // every p words are faster than crossing off bit by bit.
+        let mut mask = [0u64; 64];
+        for &(p, _) in self.base.iter().take_while(|&&(p, _)| p < 64) {
+            let mask = &mut mask[..p as usize];
+            mask.fill(u64::MAX);
+            // low + 2i ≡ 0 (mod p) for i ≡ r, with 2⁻¹ ≡ (p + 1)/2
+            let r = (p - self.low % p) * p.div_ceil(2) % p;
+            for i in (r..64 * p).step_by(p as usize) {
+                mask[(i / 64) as usize] &= !(1 << (i % 64));
+            }
+            for chunk in self.bits.chunks_mut(mask.len()) {
+                for (word, m) in chunk.iter_mut().zip(mask.iter()) {
+                    *word &= m;
+                }
+            }
+            // p itself is not a multiple to cross off
+            if (self.low..self.low + 2 * len).contains(&p) {
+                let i = (p - self.low) / 2;
+                self.bits[(i / 64) as usize] |= 1 << (i % 64);
+            }
+        }
+        for (p, next) in self.base.iter_mut().skip_while(|(p, _)| *p < 64) {
+            if *next < self.low {
+                // The first odd multiple of p at or after low; past u64::MAX
+                // it saturates to the odd u64::MAX, which no range contains
+                let m = self.low.div_ceil(*p).saturating_mul(*p);
+                *next = if m.is_multiple_of(2) { m.saturating_add(*p) } else { m };
+            }
+            let mut i = (*next - self.low) / 2;
+            while i < len {
+                self.bits[(i / 64) as usize] &= !(1 << (i % 64));
+                i += *p;
+            }
+            *next = self.low.saturating_add(2 * i);
+        }
+        if self.test {
+            for (w, word) in self.bits.iter_mut().enumerate() {
+                let mut rest = *word;
+                while rest != 0 {
+                    let bit = rest.trailing_zeros();
+                    rest &= rest - 1;
+                    if !(self.low + 2 * (w as u64 * 64 + u64::from(bit))).is_prime() {
+                        *word &= !(1 << bit);
+                    }
+                }
+            }
+        }
+        self.word = 0;
+        self.current = self.bits[0];
+    }
+}
+
+impl SegmentedSieve {
+    /// The smallest value not yet passed: every prime in the range below
+    /// it has been yielded, and none at or above it.
+    #[cfg(feature = "serde")]
+    pub(crate) fn position(&self) -> u64 {
+        if self.two {
+            return 2;
+        }
+        if self.bits.is_empty() {
+            return self.end;
+        }
+        let index = if self.current != 0 {
+            self.word as u64 * 64 + u64::from(self.current.trailing_zeros())
+        } else {
+            (self.word as u64 + 1) * 64
+        };
+        self.low.saturating_add(2 * index).min(self.end)
+    }
+}
+
+impl Iterator for SegmentedSieve {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.two {
+            self.two = false;
+            return Some(2);
+        }
+        loop {
+            if self.current != 0 {
+                let bit = self.current.trailing_zeros() as u64;
+                self.current &= self.current - 1;
+                return Some(self.low + 2 * (self.word as u64 * 64 + bit));
+            }
+            self.word += 1;
+            if self.word < self.bits.len() {
+                self.current = self.bits[self.word];
+                continue;
+            }
+            // Move on to the next segment
+            match self.low.checked_add(2 * SEGMENT_BITS) {
+                Some(low) if low < self.end && !self.bits.is_empty() => self.low = low,
+                _ => {
+                    self.bits.clear();
+                    return None;
+                }
+            }
+            self.sieve_segment();
+        }
+    }
+}
+
+/// An iterator over the factorizations of every value in a range, in
+/// ascending order, factored one segment at a time.
+///
+/// 0 and 1 have empty factorizations, as with [`PrimeFactors::factorize`].
+#[derive(Clone, Debug)]
+pub struct FactorSieve {
+    /// The primes up to √end, or up to the sieve bound if that is lower.
+    base: Vec<u64>,
+    /// The exclusive end of the range.
+    end: u64,
+    /// The value of the first entry in the current segment.
+    low: u64,
+    /// The part of each value in the segment not yet factored, and the
+    /// factors found so far.
+    rest: Vec<u64>,
+    factors: Vec<PrimeFactors>,
+    /// The index of the next value to yield in the segment.
+    index: usize,
+}
+
+impl FactorSieve {
+    /// Create an iterator over the factorizations in the half-open range.
+    #[must_use]
+    pub fn new(range: Range<u64>) -> Self {
+        let Range { start, end } = range;
+        let bound = end.saturating_sub(1).isqrt().min(FACTOR_SIEVE_BOUND);
+        let mut sieve = FactorSieve {
+            base: primes_up_to(bound),
+            end,
+            low: start,
+            rest: Vec::new(),
+            factors: Vec::new(),
+            index: 0,
+        };
+        if start < end {
+            sieve.sieve_segment();
+        }
+        sieve
+    }
+
+    /// Factor the values in the segment that starts at `low`.
+    fn sieve_segment(&mut self) {
+        let high = self.end.min(self.low.saturating_add(FACTOR_SEGMENT));
+        self.rest.clear();
+        self.rest.extend(self.low..high);
+        self.factors.clear();
+        self.factors.resize_with(self.rest.len(), PrimeFactors::new);
+        for &p in &self.base {
+            let Some(first) = self.low.div_ceil(p).checked_mul(p) else { continue };
+            for m in (first..high).step_by(p as usize) {
+                let i = (m - self.low) as usize;
+                let rest = &mut self.rest[i];
+                if *rest == 0 {
+                    continue;
+                }
+                let mut exponent = 0;
+                while rest.is_multiple_of(p) {
+                    *rest /= p;
+                    exponent += 1;
+                }
+                self.factors[i].add(p.into(), exponent);
+            }
+        }
+        let bound = self.base.last().map_or(1, |&p| p);
+        for (rest, factors) in self.rest.iter().zip(self.factors.iter_mut()) {
+            if *rest < 2 {
+                continue;
+            }
+            // Without a factor up to the bound, a cofactor below its square
+            // is prime; a larger one is factored on its own
+            if u128::from(*rest) < u128::from(bound + 1).pow(2) {
+                factors.add((*rest).into(), 1);
+            } else {
+                factors.factors.extend(PrimeFactors::factorize((*rest).into()).factors);
+            }
+        }
+        self.index = 0;
+    }
+}
+
+impl Iterator for FactorSieve {
+    type Item = (u64, PrimeFactors);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == self.rest.len() {
+            match self.low.checked_add(FACTOR_SEGMENT) {
+                Some(low) if low < self.end && !self.rest.is_empty() => self.low = low,
+                _ => {
+                    self.rest.clear();
+                    return None;
+                }
+            }
+            self.sieve_segment();
+        }
+        let n = self.low + self.index as u64;
+        let factors = std::mem::replace(&mut self.factors[self.index], PrimeFactors::new());
+        self.index += 1;
+        Some((n, factors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{primes_in_range, primes_up_to, FactorSieve, SegmentedSieve, SEGMENT_BITS};
+    use crate::{u128_is_prime, PrimeFactors};
+
+    #[test]
+    fn test_primes_up_to() {
+        assert_eq!(primes_up_to(0), Vec::<u64>::new());
+        assert_eq!(primes_up_to(1), Vec::<u64>::new());
+        assert_eq!(primes_up_to(2), vec![2]);
+        assert_eq!(primes_up_to(30), vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        assert_eq!(primes_up_to(1_000_000).len(), 78498);
+    }
+
+    #[test]
+    fn test_primes_in_range() {
+        let expected: Vec<u64> = (0..20_000u64).filter(|&n| u128_is_prime(n.into())).collect();
+        for (a, b) in [(0, 20_000), (2, 3), (3, 3), (4, 5), (7, 7919), (9, 25), (1000, 1100)] {
+            let want: Vec<u64> = expected.iter().copied().filter(|&p| a <= p && p < b).collect();
+            assert_eq!(primes_in_range(a..b), want, "{a}..{b}");
+        }
+        // Ranges that span several segments, far from zero
+        let a = (1 << 40) - 3 * SEGMENT_BITS;
+        let b = (1 << 40) + 5 * SEGMENT_BITS + 7;
+        let sieved = primes_in_range(a..b);
+        assert_eq!(sieved.len(), 605223);
+        // Compare windows around the segment boundaries with a primality test
+        for k in 0..5 {
+            let (lo, hi) = (a + 2 * k * SEGMENT_BITS - 500, a + 2 * k * SEGMENT_BITS + 500);
+            let want: Vec<u64> = (lo.max(a)..hi.min(b)).filter(|&n| u128_is_prime(n.into())).collect();
+            let got: Vec<u64> = sieved.iter().copied().filter(|&p| lo <= p && p < hi).collect();
+            assert_eq!(got, want, "window {lo}..{hi}");
+        }
+    }
+
+    #[test]
+    fn test_sieve_iterator() {
+        let mut sieve = SegmentedSieve::new(0..10_000_000);
+        assert_eq!(sieve.by_ref().take(5).collect::<Vec<_>>(), vec![2, 3, 5, 7, 11]);
+        assert_eq!(sieve.by_ref().count(), 664579 - 5);
+        assert_eq!(sieve.next(), None);
+        assert_eq!(SegmentedSieve::new(10..10).next(), None);
+        let n = 1_000_000_000_000;
+        assert_eq!(SegmentedSieve::new(n - 100..n + 1).last(), Some(999999999989));
+    }
+
+    #[test]
+    fn test_narrow_high_ranges() {
+        // Base primes stop at the width of the range, and the rest is tested
+        let ranges = [(u64::MAX - 1000, u64::MAX), (1 << 50, (1 << 50) + 100_000), ((1 << 62) - 7, 1 << 62)];
+        for (a, b) in ranges {
+            let want: Vec<u64> = (a..b).filter(|&n| u128_is_prime(n.into())).collect();
+            assert_eq!(primes_in_range(a..b), want, "{a}..{b}");
+        }
+        assert_eq!(SegmentedSieve::new(u64::MAX - 100..u64::MAX).last(), Some(u64::MAX - 58));
+        assert!(SegmentedSieve::new(u64::MAX - 1000..u64::MAX).base.len() < 10_000);
+    }
+
+    #[test]
+    fn test_factor_sieve() {
+        let ranges = [0..1000, 5..6, 65_000..200_000, (1 << 40) - 70_000..(1 << 40) + 1000];
+        for range in ranges {
+            let mut count = 0;
+            for (n, factors) in FactorSieve::new(range.clone()) {
+                assert_eq!(factors, PrimeFactors::factorize(n.into()), "{n}");
+                count += 1;
+            }
+            assert_eq!(count, range.end - range.start);
+        }
+        // Cofactors above the sieve bound are factored on their own
+        let n = u64::MAX - 100;
+        for (n, factors) in FactorSieve::new(n..n + 100) {
+            assert_eq!(factors, PrimeFactors::factorize(n.into()), "{n}");
+        }
+        assert_eq!(FactorSieve::new(u64::MAX..u64::MAX).next(), None);
+    }
+}
@@ -0,0 +1,597 @@
+//! Self-initialising quadratic sieve (SIQS).
+//!
+//! The quadratic sieve looks for many x where Q(x) = (Ax + B)² - kN is
+//! smooth over a factor base of small primes. Each such relation is a
+//! congruence (Ax + B)² ≡ Q(x) (mod N), and once there are more relations
+//! than primes, Gaussian elimination over GF(2) finds a subset whose Q
+//! values multiply to a perfect square Y², giving X² ≡ Y² (mod N) and, with
+//! probability at least one half, a proper factor gcd(X - Y, N).
+//!
+//! The self-initialising variant picks A as a product of factor base primes
+//! so that one A yields 2^(s-1) different B values cheaply, keeping every
+//! polynomial's values small. Relations with a single prime just above the
+//! factor base (partial relations) are kept and paired up when the same
+//! large prime appears twice.
+//!
+//! Unlike the other methods in this crate, the quadratic sieve's running
+//! time depends only on the size of N, which makes it the method of choice
+//! for balanced semiprimes where both factors are around 64 bits.
+//!
+//! Reference: <https://en.wikipedia.org/wiki/Quadratic_sieve>
+use std::collections::{HashMap, HashSet};
+use crate::modular::{legendre, mod_mul, mod_pow, sqrt_mod_prime};
+use crate::sieve::primes_in_range;
+use crate::{u128_gcd, u128_is_prime, PrimeNumbers};
+
+/// Inputs below this limit are rejected by [`siqs`]; the factor base and
+/// sieve interval become too small to be useful, and SQUFOF or Pollard's
+/// rho are much faster there anyway.
+pub const SIQS_MIN: u128 = 1 << 40;
+
+/// Primes below this bound are not sieved, since they hit so often that
+/// they cost more time than they contribute. The threshold accounts for
+/// the missing logarithms, and trial division still picks them up.
+const SMALL_PRIME_SKIP: u32 = 30;
+
+/// Partial relations may leave a cofactor up to this multiple of the
+/// largest factor base prime.
+const LARGE_PRIME_MULTIPLIER: u64 = 64;
+
+/// Collect this many relations beyond the factor base size, so that the
+/// matrix is guaranteed to have several independent dependencies.
+const EXTRA_RELATIONS: usize = 24;
+
+/// Give up after sieving this many polynomials.
+const MAX_POLYNOMIALS: usize = 1 << 20;
+
+/// The end of the range that factor base primes are sieved from.
+const FACTOR_BASE_SIEVE_CEILING: u64 = 1 << 16;
+
+/// Factor base size and sieve half-width for a given size of kN in bits.
+const PARAMETERS: [(u32, usize, i64); 11] = [
+    (46, 40, 2_048),
+    (50, 50, 4_096),
+    (60, 70, 8_192),
+    (70, 100, 16_384),
+    (80, 140, 32_768),
+    (90, 190, 32_768),
+    (100, 250, 32_768),
+    (110, 330, 65_536),
+    (120, 420, 65_536),
+    (130, 540, 65_536),
+    (u32::MAX, 700, 65_536),
+];
+
+/// Find a non-trivial factor of `n` with the self-initialising quadratic sieve.
+///
+/// Returns `None` when `n` is 0, 1, a prime, below [`SIQS_MIN`], or when
+/// the sieve fails to find a factor (which is very rare). The returned
+/// factor is not necessarily prime.
+#[must_use]
+pub fn siqs(n: u128) -> Option<u128> {
+    if n < SIQS_MIN || u128_is_prime(n) {
+        return None;
+    }
+    find_factor(n)
+}
+
+/// Find a non-trivial factor of the composite `n >= SIQS_MIN`.
+pub(crate) fn find_factor(n: u128) -> Option<u128> {
+    debug_assert!(n >= SIQS_MIN);
+    if n.is_multiple_of(2) {
+        return Some(2);
+    }
+    // The congruence of squares only ever yields trivial roots for a prime
+    // power, so those have to be caught first.
+    if let Some(root) = perfect_power_root(n) {
+        return Some(root);
+    }
+    let k = choose_multiplier(n);
+    let kn = k * n;
+    let bits = 128 - kn.leading_zeros();
+    let &(_, fb_size, half_width) = PARAMETERS.iter()
+        .find(|&&(max_bits, _, _)| bits <= max_bits)
+        .unwrap();
+    let fb = match FactorBase::new(n, kn, fb_size) {
+        Ok(fb) => fb,
+        Err(p) => return Some(p),
+    };
+    Sieve::new(n, kn, fb, half_width).run()
+}
+
+/// Return the smallest root r with r^e = n for some e >= 2, if any.
+fn perfect_power_root(n: u128) -> Option<u128> {
+    let bits = 128 - n.leading_zeros();
+    (2..bits).rev().find_map(|e| {
+        let mut r = (n as f64).powf(1.0 / f64::from(e)) as u128;
+        while r > 1 && r.checked_pow(e).is_none_or(|v| v > n) {
+            r -= 1;
+        }
+        while (r + 1).checked_pow(e).is_some_and(|v| v <= n) {
+            r += 1;
+        }
+        (r > 1 && r.pow(e) == n).then_some(r)
+    })
+}
+
+/// Modular exponentiation for word-sized moduli.
+fn pow_mod_u64(base: u64, exp: u64, m: u64) -> u64 {
+    mod_pow(u128::from(base), u128::from(exp), u128::from(m)) as u64
+}
+
+/// Modular inverse modulo a prime, via Fermat's little theorem.
+fn inverse_mod_prime(a: u64, p: u64) -> u64 {
+    pow_mod_u64(a, p - 2, p)
+}
+
+/// Pick a small multiplier k that makes kN a quadratic residue modulo many
+/// small primes, using the Knuth-Schroeppel function.
+fn choose_multiplier(n: u128) -> u128 {
+    const MULTIPLIERS: [u128; 20] = [
+        1, 3, 5, 7, 11, 13, 15, 17, 19, 21, 23, 29, 31, 33, 35, 37, 39, 41, 43, 47,
+    ];
+    let ln2 = std::f64::consts::LN_2;
+    let small_primes = primes_in_range(3..1000);
+    let score = |k: u128| {
+        let kn = k * n;
+        let mut s = -0.5 * (k as f64).ln();
+        s += match kn % 8 {
+            1 => 2.0 * ln2,
+            5 => ln2,
+            _ => 0.5 * ln2,
+        };
+        for &p in &small_primes {
+            let lnp = (p as f64).ln();
+            if k.is_multiple_of(u128::from(p)) {
+                s += lnp / p as f64;
+            } else if legendre(kn, u128::from(p)) == 1 {
+                s += 2.0 * lnp / (p - 1) as f64;
+            }
+        }
+        s
+    };
+    MULTIPLIERS.iter()
+        .copied()
+        .filter(|&k| k.checked_mul(n).is_some_and(|kn| kn < u128::MAX / 2))
+        .map(|k| (k, score(k)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(1, |(k, _)| k)
+}
+
+/// The primes modulo which kN is a square, with those square roots.
+struct FactorBase {
+    primes: Vec<u32>,
+    roots: Vec<u32>,
+    logs: Vec<u8>,
+}
+
+impl FactorBase {
+    /// Build a factor base of `size` primes. If a prime turns out to divide
+    /// n, it is returned as the error instead.
+    fn new(n: u128, kn: u128, size: usize) -> Result<Self, u128> {
+        let mut fb = FactorBase {
+            primes: vec![2],
+            roots: vec![1],
+            logs: vec![1],
+        };
+        // The largest factor base needs the first 1400 or so primes, all of
+        // them below the sieve ceiling; the wheel takes over past it.
+        for p in PrimeNumbers::from(3).sieve_ceiling(FACTOR_BASE_SIEVE_CEILING) {
+            if fb.primes.len() >= size {
+                break;
+            }
+            if n.is_multiple_of(p) {
+                return Err(p);
+            }
+            let p = p as u64;
+            let r = (kn % u128::from(p)) as u64;
+            // p divides the multiplier when r is 0, and 0 is its root
+            let Some(root) = sqrt_mod_prime(r.into(), p.into()) else {
+                continue;
+            };
+            fb.primes.push(p as u32);
+            fb.roots.push(root as u32);
+            fb.logs.push((p as f64).log2().round() as u8);
+        }
+        Ok(fb)
+    }
+
+    fn largest(&self) -> u64 {
+        u64::from(*self.primes.last().unwrap())
+    }
+}
+
+/// A congruence X² ≡ ∏ p^e · ∏ L² (mod N): `y` is X, `factors` lists the
+/// factor base indices of the primes p (with repetition), `large` holds the
+/// large primes L, and `negative` records a factor of -1.
+#[derive(Clone, Debug)]
+struct Relation {
+    y: u128,
+    factors: Vec<u32>,
+    large: Vec<u128>,
+    negative: bool,
+}
+
+/// A simple xorshift generator, to pick the primes of A reproducibly.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+struct Sieve {
+    n: u128,
+    kn: u128,
+    fb: FactorBase,
+    half_width: i64,
+    large_prime_bound: u128,
+    threshold: u8,
+    rng: XorShift,
+    used_a: HashSet<u128>,
+    seen_y: HashSet<u128>,
+    relations: Vec<Relation>,
+    partials: HashMap<u128, Relation>,
+}
+
+impl Sieve {
+    fn new(n: u128, kn: u128, fb: FactorBase, half_width: i64) -> Self {
+        let p_max = fb.largest();
+        let large_prime_bound = u128::from(p_max * LARGE_PRIME_MULTIPLIER.min(p_max));
+        // Values of Q(x)/A are at most M·√(kN/2); allow the cofactor of a
+        // partial relation and the skipped small primes to go missing.
+        let log_q = (half_width as f64).log2() + (kn as f64).log2() / 2.0 - 0.5;
+        let slack = (large_prime_bound as f64).log2() + 2.0;
+        let threshold = (log_q - slack).max(1.0) as u8;
+        Sieve {
+            n,
+            kn,
+            fb,
+            half_width,
+            large_prime_bound,
+            threshold,
+            rng: XorShift(0x9E37_79B9_7F4A_7C15),
+            used_a: HashSet::new(),
+            seen_y: HashSet::new(),
+            relations: Vec::new(),
+            partials: HashMap::new(),
+        }
+    }
+
+    /// Sieve polynomials until there are enough relations, then look for a
+    /// congruence of squares among them.
+    fn run(mut self) -> Option<u128> {
+        let needed = self.fb.primes.len() + 1 + EXTRA_RELATIONS;
+        let mut polynomials = 0;
+        while self.relations.len() < needed {
+            let (a, a_indices) = self.choose_a()?;
+            let b_terms = self.b_terms(a, &a_indices);
+            // B and -B give mirror-image polynomials, so fix the sign of the
+            // first term and vary the rest.
+            for signs in 0..1_u64 << (b_terms.len() - 1) {
+                let b = b_terms.iter().enumerate().fold(0_i128, |acc, (j, &bj)| {
+                    if j > 0 && (signs >> (j - 1)) & 1 == 1 { acc - bj } else { acc + bj }
+                });
+                let b = b.rem_euclid(a as i128) as u128;
+                self.sieve_polynomial(a, b, &a_indices);
+                polynomials += 1;
+                if polynomials >= MAX_POLYNOMIALS {
+                    return None;
+                }
+            }
+        }
+        self.combine()
+    }
+
+    /// Pick A as a product of factor base primes close to √(2kN)/M. Returns
+    /// the factor base indices of its primes too.
+    fn choose_a(&mut self) -> Option<(u128, Vec<usize>)> {
+        let target = ((2.0 * self.kn as f64).sqrt() / self.half_width as f64).log2();
+        let primes = &self.fb.primes;
+        // Usable primes: sieved ones that do not divide the multiplier.
+        let usable: Vec<usize> = (0..primes.len())
+            .filter(|&i| primes[i] >= SMALL_PRIME_SKIP && self.fb.roots[i] != 0)
+            .collect();
+        let log_p = |i: usize| f64::from(primes[i]).log2();
+        let largest = log_p(*usable.last()?);
+        // Use as few primes as possible while keeping them inside the
+        // factor base, since larger primes make for fewer lost relations.
+        let mut s = ((target / 11.0).round() as usize).max(1);
+        while target / s as f64 > largest - 1.0 {
+            s += 1;
+        }
+        let ideal = target / s as f64;
+        // Widen the window of candidate primes whenever the current one
+        // runs out of fresh values of A, which happens for small inputs.
+        for width in [1.0, 1.5, 2.0, 3.0, 4.0] {
+            let window: Vec<usize> = usable.iter()
+                .copied()
+                .filter(|&i| (log_p(i) - ideal).abs() <= width)
+                .collect();
+            if window.len() < s + 2 {
+                continue;
+            }
+            for _ in 0..200 {
+                let mut chosen: Vec<usize> = Vec::with_capacity(s);
+                let mut log_a = 0.0;
+                while chosen.len() + 1 < s {
+                    let i = window[self.rng.next() as usize % window.len()];
+                    if !chosen.contains(&i) {
+                        chosen.push(i);
+                        log_a += log_p(i);
+                    }
+                }
+                // Pick the last prime to bring A as close to the target as possible.
+                let last = usable.iter()
+                    .copied()
+                    .filter(|i| !chosen.contains(i))
+                    .min_by(|&i, &j| {
+                        let di = (log_a + log_p(i) - target).abs();
+                        let dj = (log_a + log_p(j) - target).abs();
+                        di.total_cmp(&dj)
+                    })?;
+                chosen.push(last);
+                chosen.sort_unstable();
+                let a: u128 = chosen.iter().map(|&i| u128::from(primes[i])).product();
+                if self.used_a.insert(a) {
+                    return Some((a, chosen));
+                }
+            }
+        }
+        None
+    }
+
+    /// The terms B_j = (A/q_j)·γ_j with B_j² ≡ kN (mod q_j) and B_j ≡ 0
+    /// modulo the other primes of A; any signed sum of them is a valid B.
+    fn b_terms(&self, a: u128, a_indices: &[usize]) -> Vec<i128> {
+        a_indices.iter().map(|&i| {
+            let q = u64::from(self.fb.primes[i]);
+            let a_q = a / u128::from(q);
+            let inv = inverse_mod_prime((a_q % u128::from(q)) as u64, q);
+            let mut gamma = u64::from(self.fb.roots[i]) * inv % q;
+            if gamma > q / 2 {
+                gamma = q - gamma;
+            }
+            (a_q * u128::from(gamma)) as i128
+        }).collect()
+    }
+
+    /// Sieve one polynomial Q(x) = A·x² + 2B·x + C over [-M, M) and
+    /// record the relations it yields.
+    fn sieve_polynomial(&mut self, a: u128, b: u128, a_indices: &[usize]) {
+        debug_assert!(mod_mul(b, b, a) == self.kn % a);
+        let c = (self.kn - b * b) / a;
+        let m = self.half_width;
+        let len = 2 * m as usize;
+        let fb = &self.fb;
+        // The sieve offsets of both roots for every sieved prime.
+        let mut offsets = vec![(u32::MAX, u32::MAX); fb.primes.len()];
+        for (i, &p) in fb.primes.iter().enumerate() {
+            let root = fb.roots[i];
+            if p < SMALL_PRIME_SKIP || root == 0 || a_indices.contains(&i) {
+                continue;
+            }
+            let p64 = u64::from(p);
+            let a_inv = inverse_mod_prime((a % u128::from(p)) as u64, p64);
+            let b_mod = (b % u128::from(p)) as u64;
+            let m_mod = m as u64 % p64;
+            let r1 = (u64::from(root) + p64 - b_mod) % p64 * a_inv % p64;
+            let r2 = (2 * p64 - u64::from(root) - b_mod) % p64 * a_inv % p64;
+            offsets[i] = (((r1 + m_mod) % p64) as u32, ((r2 + m_mod) % p64) as u32);
+        }
+        let mut sieve = vec![0_u8; len];
+        for (i, &(o1, o2)) in offsets.iter().enumerate() {
+            if o1 == u32::MAX {
+                continue;
+            }
+            let p = fb.primes[i] as usize;
+            let logp = fb.logs[i];
+            for o in [o1, o2] {
+                for s in sieve.iter_mut().skip(o as usize).step_by(p) {
+                    *s += logp;
+                }
+            }
+        }
+        for (idx, &s) in sieve.iter().enumerate() {
+            if s < self.threshold {
+                continue;
+            }
+            let x = idx as i128 - m as i128;
+            let q = (a as i128) * x * x + 2 * (b as i128) * x - c as i128;
+            if q == 0 {
+                continue;
+            }
+            if let Some(rel) = self.trial_divide(a, b, x, q, a_indices, idx, &offsets) {
+                self.add_relation(rel);
+            }
+        }
+    }
+
+    /// Factor Q(x)/A over the factor base, keeping full relations and partial
+    /// relations with a single large prime.
+    #[allow(clippy::too_many_arguments)]
+    fn trial_divide(&self, a: u128, b: u128, x: i128, q: i128, a_indices: &[usize],
+                    idx: usize, offsets: &[(u32, u32)]) -> Option<(Relation, u128)> {
+        let mut rest = q.unsigned_abs();
+        let mut factors: Vec<u32> = a_indices.iter().map(|&i| i as u32).collect();
+        for (i, &p) in self.fb.primes.iter().enumerate() {
+            let (o1, o2) = offsets[i];
+            if o1 != u32::MAX {
+                let r = (idx % p as usize) as u32;
+                if r != o1 && r != o2 {
+                    continue;
+                }
+            }
+            let p = u128::from(p);
+            while rest.is_multiple_of(p) {
+                rest /= p;
+                factors.push(i as u32);
+            }
+        }
+        if rest >= self.large_prime_bound {
+            return None;
+        }
+        // X = Ax + B, reduced modulo N
+        let ax_b = a as i128 * x + b as i128;
+        let y = if ax_b >= 0 {
+            ax_b.unsigned_abs() % self.n
+        } else {
+            (self.n - ax_b.unsigned_abs() % self.n) % self.n
+        };
+        let rel = Relation { y, factors, large: Vec::new(), negative: q < 0 };
+        Some((rel, rest))
+    }
+
+    /// Store a full relation, or pair a partial relation with an earlier one
+    /// that shares its large prime.
+    fn add_relation(&mut self, (rel, large): (Relation, u128)) {
+        if !self.seen_y.insert(rel.y) {
+            return;
+        }
+        if large == 1 {
+            self.relations.push(rel);
+            return;
+        }
+        match self.partials.get(&large) {
+            Some(other) => {
+                let mut factors = other.factors.clone();
+                factors.extend_from_slice(&rel.factors);
+                self.relations.push(Relation {
+                    y: mod_mul(other.y, rel.y, self.n),
+                    factors,
+                    large: vec![large],
+                    negative: other.negative != rel.negative,
+                });
+            }
+            None => {
+                self.partials.insert(large, rel);
+            }
+        }
+    }
+
+    /// Find dependencies among the relations and turn each into a
+    /// congruence of squares until one of them splits N.
+    fn combine(&self) -> Option<u128> {
+        let n = self.n;
+        let cols = self.fb.primes.len() + 1;
+        let vectors: Vec<Vec<u64>> = self.relations.iter().map(|rel| {
+            let mut v = vec![0_u64; cols.div_ceil(64)];
+            if rel.negative {
+                v[0] ^= 1;
+            }
+            for &f in &rel.factors {
+                let c = f as usize + 1;
+                v[c / 64] ^= 1 << (c % 64);
+            }
+            v
+        }).collect();
+        for dep in gf2_dependencies(vectors, cols) {
+            let mut x: u128 = 1;
+            let mut y: u128 = 1;
+            let mut exponents = vec![0_u32; self.fb.primes.len()];
+            for &r in &dep {
+                let rel = &self.relations[r];
+                x = mod_mul(x, rel.y, n);
+                for &f in &rel.factors {
+                    exponents[f as usize] += 1;
+                }
+                for &l in &rel.large {
+                    y = mod_mul(y, l % n, n);
+                }
+            }
+            for (i, &e) in exponents.iter().enumerate() {
+                debug_assert!(e % 2 == 0);
+                if e > 0 {
+                    let p = u128::from(self.fb.primes[i]);
+                    y = mod_mul(y, mod_pow(p, u128::from(e / 2), n), n);
+                }
+            }
+            let g = u128_gcd(x.abs_diff(y), n);
+            if g > 1 && g < n {
+                return Some(g);
+            }
+        }
+        None
+    }
+}
+
+/// Gaussian elimination over GF(2). Each row is a bit vector of `cols`
+/// columns; returns the sets of row indices whose vectors sum to zero.
+fn gf2_dependencies(mut rows: Vec<Vec<u64>>, cols: usize) -> Vec<Vec<usize>> {
+    let nrows = rows.len();
+    let words = nrows.div_ceil(64);
+    let mut history: Vec<Vec<u64>> = (0..nrows).map(|r| {
+        let mut h = vec![0_u64; words];
+        h[r / 64] |= 1 << (r % 64);
+        h
+    }).collect();
+    let mut pivot = 0;
+    for col in 0..cols {
+        let bit = |row: &[u64]| (row[col / 64] >> (col % 64)) & 1 == 1;
+        let Some(r) = (pivot..nrows).find(|&r| bit(&rows[r])) else {
+            continue;
+        };
+        rows.swap(pivot, r);
+        history.swap(pivot, r);
+        let pivot_row = rows[pivot].clone();
+        let pivot_history = history[pivot].clone();
+        for other in 0..nrows {
+            if other != pivot && bit(&rows[other]) {
+                rows[other].iter_mut().zip(&pivot_row).for_each(|(d, s)| *d ^= s);
+                history[other].iter_mut().zip(&pivot_history).for_each(|(d, s)| *d ^= s);
+            }
+        }
+        pivot += 1;
+    }
+    history[pivot..].iter().map(|h| {
+        (0..nrows).filter(|&r| (h[r / 64] >> (r % 64)) & 1 == 1).collect()
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gf2_dependencies, perfect_power_root, siqs, SIQS_MIN};
+
+    #[test]
+    fn test_perfect_power_root() {
+        assert_eq!(perfect_power_root(1000003 * 1000003), Some(1000003));
+        assert_eq!(perfect_power_root(3_u128.pow(80)), Some(3));
+        assert_eq!(perfect_power_root(65537_u128.pow(7)), Some(65537));
+        assert_eq!(perfect_power_root(1000003 * 1000033), None);
+        assert_eq!(perfect_power_root(u128::MAX), None);
+    }
+
+    #[test]
+    fn test_gf2_dependencies() {
+        // Rows 0 ^ 1 == 2 and row 3 is zero
+        let rows = vec![vec![0b011], vec![0b110], vec![0b101], vec![0b000]];
+        let deps = gf2_dependencies(rows, 3);
+        assert_eq!(deps.len(), 2);
+        assert!(deps.contains(&vec![3]));
+        assert!(deps.contains(&vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn test_siqs_rejects_trivial_inputs() {
+        for n in [0, 1, 4, 1000003 * 1000033, SIQS_MIN - 1, 2305843009213693951] {
+            assert_eq!(siqs(n), None, "siqs({n})");
+        }
+    }
+
+    #[test]
+    fn test_siqs_balanced_semiprimes() {
+        let cases: [(u128, u128); 3] = [
+            (1099511627791, 1099511627803),
+            (4294967291, 2305843009213693951),
+            (18446744073709551557, 4294967291),
+        ];
+        for (p, q) in cases {
+            let d = siqs(p * q).unwrap();
+            assert!(d == p || d == q, "{d} is not a factor of {p} * {q}");
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Shanks' square forms factorization (SQUFOF).
+//!
+//! SQUFOF walks the continued fraction expansion of √(kN) looking for a
+//! square denominator, then walks a second, reduced form until it reaches
+//! a symmetry point that exposes a factor. It needs O(N^¼) iterations and
+//! only integer arithmetic, with no tables beyond a short multiplier list,
+//! which makes it a good fit for cofactors of up to about 62 bits.
+//!
+//! Reference: <https://en.wikipedia.org/wiki/Shanks%27s_square_forms_factorization>
+use crate::{u128_gcd, u128_is_prime};
+
+/// Inputs at or above this limit are rejected by [`squfof`].
+pub const SQUFOF_LIMIT: u128 = 1 << 62;
+
+/// Multipliers tried in turn; each one gives the continued fraction a new
+/// chance of hitting a useful square form.
+const MULTIPLIERS: [u128; 16] = [
+    1, 3, 5, 7, 11, 3 * 5, 3 * 7, 3 * 11, 5 * 7, 5 * 11, 7 * 11,
+    3 * 5 * 7, 3 * 5 * 11, 3 * 7 * 11, 5 * 7 * 11, 3 * 5 * 7 * 11,
+];
+
+/// Find a non-trivial factor of `n` using Shanks' square forms factorization.
+///
+/// Returns `None` when `n` is 0, 1, a prime, at least [`SQUFOF_LIMIT`], or
+/// when every multiplier fails to produce a split (which is rare). The
+/// returned factor is not necessarily prime.
+#[must_use]
+pub fn squfof(n: u128) -> Option<u128> {
+    if !(4..SQUFOF_LIMIT).contains(&n) || u128_is_prime(n) {
+        return None;
+    }
+    find_factor(n)
+}
+
+/// Find a non-trivial factor of the composite `n < SQUFOF_LIMIT`.
+pub(crate) fn find_factor(n: u128) -> Option<u128> {
+    debug_assert!((4..SQUFOF_LIMIT).contains(&n));
+    if n.is_multiple_of(2) {
+        return Some(2);
+    }
+    let root = n.isqrt();
+    if root * root == n {
+        return Some(root);
+    }
+    MULTIPLIERS.iter().find_map(|&k| squfof_multiplier(n, k))
+}
+
+/// Run SQUFOF on k·n. The intermediate P and Q values stay below 2·√(kn),
+/// so signed 128-bit arithmetic leaves plenty of headroom.
+fn squfof_multiplier(n: u128, k: u128) -> Option<u128> {
+    let kn = (k * n) as i128;
+    let p0 = kn.isqrt();
+    let mut q = kn - p0 * p0;
+    if q == 0 {
+        // k·n is a perfect square; any shared factor with n is useful.
+        let g = u128_gcd(n, p0 as u128);
+        return (g > 1 && g < n).then_some(g);
+    }
+    // The forward cycle has length O(√(2√kn)); give up well after that.
+    let bound = 6 * (2 * p0).isqrt();
+    let mut p = p0;
+    let mut p_prev;
+    let mut q_prev = 1;
+    let mut r = 0;
+    let mut found = false;
+    for i in 2..bound {
+        let b = (p0 + p) / q;
+        p_prev = p;
+        p = b * q - p;
+        let q_next = q_prev + b * (p_prev - p);
+        q_prev = q;
+        q = q_next;
+        // Q_i is a square form only for even i
+        if i % 2 == 0 {
+            r = q.isqrt();
+            if r * r == q {
+                found = true;
+                break;
+            }
+        }
+    }
+    if !found {
+        return None;
+    }
+    // Reverse cycle: start from the square root of the form and walk until
+    // P repeats, at which point Q shares a factor with n.
+    let b = (p0 - p) / r;
+    p += b * r;
+    q_prev = r;
+    q = (kn - p * p) / q_prev;
+    loop {
+        let b = (p0 + p) / q;
+        p_prev = p;
+        p = b * q - p;
+        let q_next = q_prev + b * (p_prev - p);
+        q_prev = q;
+        q = q_next;
+        if p == p_prev {
+            break;
+        }
+    }
+    let g = u128_gcd(n, q_prev as u128);
+    (g > 1 && g < n).then_some(g)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{squfof, SQUFOF_LIMIT};
+
+    #[test]
+    fn test_squfof_limit() {
+        for n in [SQUFOF_LIMIT, SQUFOF_LIMIT + 1, 1000003 * 2305843009213693951] {
+            assert_eq!(squfof(n), None, "squfof({n})");
+        }
+    }
+
+    #[test]
+    fn test_squfof_semiprimes() {
+        let cases: [(u128, u128); 6] = [
+            (11, 13),
+            (101, 103),
+            (65521, 65537),
+            (1000003, 1000033),
+            (1000000007, 998244353),
+            (2147483647, 2147483629),
+        ];
+        for (p, q) in cases {
+            let d = squfof(p * q).unwrap();
+            assert!(d == p || d == q, "{d} is not a factor of {p} * {q}");
+        }
+    }
+
+    #[test]
+    fn test_squfof_squares_and_composites() {
+        assert_eq!(squfof(1000003 * 1000003), Some(1000003));
+        for n in (1_000_001..1_002_001_u128).step_by(2) {
+            if let Some(d) = squfof(n) {
+                assert!(d > 1 && d < n && n.is_multiple_of(d), "bad split {d} of {n}");
+            }
+        }
+    }
+}
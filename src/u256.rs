@@ -0,0 +1,643 @@
+//! A 256-bit unsigned integer for factoring and primality testing beyond
+//! `u128`.
+//!
+//! [`U256`] implements [`PrimeInt`], so it works with the same API as the
+//! primitive types: `Factorization<U256>`, `Primes<U256>`, and the
+//! `is_prime`, `next_prime` and `try_factorize` methods. Values that fit in
+//! `u128` are handed to the `u128` code. Above that, primality is decided
+//! by the Baillie–PSW test (see [`crate::bpsw`]) and Miller–Rabin to the
+//! first twelve prime bases, since a proof would need the factorization of
+//! n − 1, and composites are split by trial division
+//! and Pollard's rho until the cofactors fit in `u128`, after which every
+//! `u128` method is available. Rho is given a budget that reaches factors
+//! of about 48 bits; a number with a cofactor above `u128` that has two
+//! prime factors larger than that cannot be factored, and
+//! `Factorization::try_factorize` returns `None` for it. For this reason
+//! `U256` is not [`crate::Factorable`] and has no infallible `factorize`.
+//!
+//! Reference: <https://en.wikipedia.org/wiki/Montgomery_modular_multiplication>
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{
+    Add, AddAssign, BitAnd, BitOr, Div, DivAssign, Mul, MulAssign, Rem, RemAssign, Shl, Shr, ShrAssign,
+    Sub, SubAssign,
+};
+use crate::montgomery::{mul_wide, MontgomeryArith};
+use crate::primeint::PrimeInt;
+use crate::{bpsw, pollard, small_prime_test, u128_is_prime};
+
+/// A 256-bit unsigned integer. Arithmetic panics on overflow, and division
+/// panics on a zero divisor.
+#[derive(Clone, Copy, Default, Eq, Hash, PartialEq)]
+pub struct U256 {
+    /// The four 64-bit limbs, least significant first.
+    limbs: [u64; 4],
+}
+
+impl U256 {
+    pub const ZERO: Self = Self { limbs: [0; 4] };
+    pub const ONE: Self = Self { limbs: [1, 0, 0, 0] };
+    pub const MAX: Self = Self { limbs: [u64::MAX; 4] };
+
+    /// The value hi·2¹²⁸ + lo.
+    #[must_use]
+    pub const fn from_words(hi: u128, lo: u128) -> Self {
+        Self { limbs: [lo as u64, (lo >> 64) as u64, hi as u64, (hi >> 64) as u64] }
+    }
+    /// The high and low 128-bit halves.
+    #[must_use]
+    pub const fn to_words(self) -> (u128, u128) {
+        let [l0, l1, l2, l3] = self.limbs;
+        (((l3 as u128) << 64) | l2 as u128, ((l1 as u128) << 64) | l0 as u128)
+    }
+    /// The full product of two `u128` values.
+    #[must_use]
+    pub fn from_product(a: u128, b: u128) -> Self {
+        let (hi, lo) = mul_wide(a, b);
+        Self::from_words(hi, lo)
+    }
+    /// The value of 32 big-endian bytes, such as a SHA-256 digest.
+    #[must_use]
+    pub fn from_be_bytes(bytes: [u8; 32]) -> Self {
+        let mut limbs = [0; 4];
+        for (limb, chunk) in limbs.iter_mut().rev().zip(bytes.chunks_exact(8)) {
+            *limb = u64::from_be_bytes(chunk.try_into().unwrap());
+        }
+        Self { limbs }
+    }
+    /// The value as 32 big-endian bytes.
+    #[must_use]
+    pub fn to_be_bytes(self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        for (chunk, limb) in bytes.chunks_exact_mut(8).zip(self.limbs.iter().rev()) {
+            chunk.copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes
+    }
+    #[must_use]
+    pub fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+        let mut limbs = [0; 4];
+        let mut carry = false;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let (s, c1) = self.limbs[i].overflowing_add(rhs.limbs[i]);
+            let (s, c2) = s.overflowing_add(u64::from(carry));
+            *limb = s;
+            carry = c1 || c2;
+        }
+        (Self { limbs }, carry)
+    }
+    #[must_use]
+    pub fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+        let mut limbs = [0; 4];
+        let mut borrow = false;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let (d, b1) = self.limbs[i].overflowing_sub(rhs.limbs[i]);
+            let (d, b2) = d.overflowing_sub(u64::from(borrow));
+            *limb = d;
+            borrow = b1 || b2;
+        }
+        (Self { limbs }, borrow)
+    }
+    #[must_use]
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        let (sum, overflow) = self.overflowing_add(rhs);
+        (!overflow).then_some(sum)
+    }
+    #[must_use]
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        let (diff, overflow) = self.overflowing_sub(rhs);
+        (!overflow).then_some(diff)
+    }
+    #[must_use]
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        let wide = wide_mul(self, rhs);
+        wide[4..].iter().all(|&l| l == 0).then(|| Self { limbs: wide[..4].try_into().unwrap() })
+    }
+    /// Returns `None` when `rhs` is zero.
+    #[must_use]
+    pub fn checked_div_rem(self, rhs: Self) -> Option<(Self, Self)> {
+        if rhs == Self::ZERO {
+            return None;
+        }
+        if rhs.limbs[1..].iter().all(|&l| l == 0) {
+            let (q, r) = self.div_rem_u64(rhs.limbs[0]);
+            return Some((q, Self::from(r)));
+        }
+        // Binary long division; the divisor has more than 64 bits, so the
+        // quotient has at most 192.
+        let mut q = Self::ZERO;
+        let mut r = Self::ZERO;
+        for bit in (0..256 - self.leading_zeros()).rev() {
+            let carry = r.limbs[3] >> 63 == 1;
+            r = r << 1;
+            r.limbs[0] |= (self.limbs[bit as usize / 64] >> (bit % 64)) & 1;
+            if carry || r >= rhs {
+                r = r.overflowing_sub(rhs).0;
+                q.limbs[bit as usize / 64] |= 1 << (bit % 64);
+            }
+        }
+        Some((q, r))
+    }
+    /// Division by a single limb, which is all that trial division needs.
+    fn div_rem_u64(self, d: u64) -> (Self, u64) {
+        let d = u128::from(d);
+        let mut limbs = [0; 4];
+        let mut r: u128 = 0;
+        for i in (0..4).rev() {
+            let cur = (r << 64) | u128::from(self.limbs[i]);
+            limbs[i] = (cur / d) as u64;
+            r = cur % d;
+        }
+        (Self { limbs }, r as u64)
+    }
+    #[must_use]
+    pub fn leading_zeros(self) -> u32 {
+        match self.limbs.iter().rposition(|&l| l != 0) {
+            Some(i) => (3 - i as u32) * 64 + self.limbs[i].leading_zeros(),
+            None => 256,
+        }
+    }
+    #[must_use]
+    pub fn trailing_zeros(self) -> u32 {
+        match self.limbs.iter().position(|&l| l != 0) {
+            Some(i) => i as u32 * 64 + self.limbs[i].trailing_zeros(),
+            None => 256,
+        }
+    }
+    /// The integer square root ⌊√self⌋.
+    #[must_use]
+    pub fn isqrt(self) -> Self {
+        if let Some(n) = self.to_u128() {
+            return Self::from(n.isqrt());
+        }
+        // Newton's iteration from above converges monotonically.
+        let mut x = Self::ONE << (256 - self.leading_zeros()).div_ceil(2);
+        loop {
+            let y = (x + self / x) >> 1;
+            if y >= x {
+                return x;
+            }
+            x = y;
+        }
+    }
+    /// The value as a `u128`, if it fits.
+    #[must_use]
+    pub fn to_u128(self) -> Option<u128> {
+        let (hi, lo) = self.to_words();
+        (hi == 0).then_some(lo)
+    }
+    /// Test if the value is a prime number.
+    ///
+    /// Values that fit in `u128` get the proven test of
+    /// [`crate::u128_is_prime`]. Above that, a value must pass the
+    /// Baillie–PSW test and Miller–Rabin to the remaining eleven of the
+    /// first twelve prime bases. No composite is known to pass even BPSW
+    /// alone, but unlike the `u128` default neither is a proof of
+    /// primality.
+    #[must_use]
+    pub fn is_prime(self) -> bool {
+        const WITNESSES: [u128; 11] = [3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+        if let Some(n) = self.to_u128() {
+            return u128_is_prime(n);
+        }
+        if let Some(prime) = small_prime_test(self) {
+            return prime;
+        }
+        // BPSW includes the strong test to base 2
+        Montgomery256::new(self).is_some_and(|mont| {
+            bpsw::is_probable_prime(&mont)
+                && WITNESSES.iter().all(|&a| bpsw::strong_probable_prime(&mont, U256::from(a)))
+        })
+    }
+}
+
+/// The full 512-bit product, as eight limbs.
+fn wide_mul(a: U256, b: U256) -> [u64; 8] {
+    let mut t = [0u64; 8];
+    for i in 0..4 {
+        let mut carry: u128 = 0;
+        for j in 0..4 {
+            let cur = u128::from(t[i + j])
+                + u128::from(a.limbs[j]) * u128::from(b.limbs[i])
+                + carry;
+            t[i + j] = cur as u64;
+            carry = cur >> 64;
+        }
+        t[i + 4] = carry as u64;
+    }
+    t
+}
+
+impl Ord for U256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs.iter().rev().cmp(other.limbs.iter().rev())
+    }
+}
+
+impl PartialOrd for U256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl From<u8> for U256 {
+    fn from(n: u8) -> Self {
+        Self::from(u128::from(n))
+    }
+}
+
+impl From<u32> for U256 {
+    fn from(n: u32) -> Self {
+        Self::from(u128::from(n))
+    }
+}
+
+impl From<u64> for U256 {
+    fn from(n: u64) -> Self {
+        Self::from(u128::from(n))
+    }
+}
+
+impl From<u128> for U256 {
+    fn from(n: u128) -> Self {
+        Self::from_words(0, n)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const CHUNK: u64 = 10_000_000_000_000_000_000;
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while n >= Self::from(CHUNK) {
+            let (q, r) = n.div_rem_u64(CHUNK);
+            chunks.push(r);
+            n = q;
+        }
+        let mut s = n.to_u128().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            s.push_str(&format!("{chunk:019}"));
+        }
+        f.pad_integral(true, "", &s)
+    }
+}
+
+impl fmt::Debug for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Add for U256 {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        self.checked_add(rhs).expect("attempt to add with overflow")
+    }
+}
+
+impl Sub for U256 {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self.checked_sub(rhs).expect("attempt to subtract with overflow")
+    }
+}
+
+impl Mul for U256 {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        self.checked_mul(rhs).expect("attempt to multiply with overflow")
+    }
+}
+
+impl Div for U256 {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        self.checked_div_rem(rhs).expect("attempt to divide by zero").0
+    }
+}
+
+impl Rem for U256 {
+    type Output = Self;
+    fn rem(self, rhs: Self) -> Self {
+        self.checked_div_rem(rhs).expect("attempt to calculate the remainder with a divisor of zero").1
+    }
+}
+
+impl BitAnd for U256 {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self {
+        let mut limbs = self.limbs;
+        for (l, r) in limbs.iter_mut().zip(rhs.limbs) {
+            *l &= r;
+        }
+        Self { limbs }
+    }
+}
+
+impl BitOr for U256 {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        let mut limbs = self.limbs;
+        for (l, r) in limbs.iter_mut().zip(rhs.limbs) {
+            *l |= r;
+        }
+        Self { limbs }
+    }
+}
+
+impl Shl<u32> for U256 {
+    type Output = Self;
+    fn shl(self, shift: u32) -> Self {
+        let (words, bits) = (shift as usize / 64, shift % 64);
+        let mut limbs = [0; 4];
+        for (i, limb) in limbs.iter_mut().enumerate().skip(words) {
+            *limb = self.limbs[i - words] << bits;
+            if bits > 0 && i > words {
+                *limb |= self.limbs[i - words - 1] >> (64 - bits);
+            }
+        }
+        Self { limbs }
+    }
+}
+
+impl Shr<u32> for U256 {
+    type Output = Self;
+    fn shr(self, shift: u32) -> Self {
+        let (words, bits) = (shift as usize / 64, shift % 64);
+        let mut limbs = [0; 4];
+        for (i, limb) in limbs.iter_mut().take(4usize.saturating_sub(words)).enumerate() {
+            *limb = self.limbs[i + words] >> bits;
+            if bits > 0 && i + words + 1 < 4 {
+                *limb |= self.limbs[i + words + 1] << (64 - bits);
+            }
+        }
+        Self { limbs }
+    }
+}
+
+macro_rules! assign_ops {
+    ($($trait:ident $method:ident $op:tt $rhs:ty),*) => {
+        $(impl $trait<$rhs> for U256 {
+            fn $method(&mut self, rhs: $rhs) {
+                *self = *self $op rhs;
+            }
+        })*
+    };
+}
+
+assign_ops!(
+    AddAssign add_assign + U256,
+    SubAssign sub_assign - U256,
+    MulAssign mul_assign * U256,
+    DivAssign div_assign / U256,
+    RemAssign rem_assign % U256,
+    ShrAssign shr_assign >> u32
+);
+
+impl PrimeInt for U256 {
+    const ZERO: Self = U256::ZERO;
+    const ONE: Self = U256::ONE;
+    const MAX: Self = U256::MAX;
+    const BITS: u32 = 256;
+
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        U256::checked_add(self, rhs)
+    }
+    fn checked_mul(self, rhs: Self) -> Option<Self> {
+        U256::checked_mul(self, rhs)
+    }
+    fn pow(self, exp: u32) -> Self {
+        (0..exp).fold(Self::ONE, |acc, _| acc * self)
+    }
+    fn trailing_zeros(self) -> u32 {
+        U256::trailing_zeros(self)
+    }
+    fn leading_zeros(self) -> u32 {
+        U256::leading_zeros(self)
+    }
+    fn is_multiple_of(self, rhs: Self) -> bool {
+        if rhs == Self::ZERO {
+            return self == Self::ZERO;
+        }
+        self % rhs == Self::ZERO
+    }
+    fn isqrt(self) -> Self {
+        U256::isqrt(self)
+    }
+    fn to_u128(self) -> Option<u128> {
+        U256::to_u128(self)
+    }
+    fn from_u128(n: u128) -> Self {
+        Self::from(n)
+    }
+    /// Proven for values that fit in `u128`; above that, a probable prime
+    /// test. See [`U256::is_prime`].
+    fn is_prime(self) -> bool {
+        U256::is_prime(self)
+    }
+    /// Binary gcd, which avoids the long divisions of Euclid's algorithm.
+    fn gcd(self, other: Self) -> Self {
+        let (mut a, mut b) = (self, other);
+        if a == Self::ZERO || b == Self::ZERO {
+            return a.overflowing_add(b).0;
+        }
+        let shift = U256::trailing_zeros(a | b);
+        a = a >> U256::trailing_zeros(a);
+        loop {
+            b = b >> U256::trailing_zeros(b);
+            if a > b {
+                (a, b) = (b, a);
+            }
+            b -= a;
+            if b == Self::ZERO {
+                return a << shift;
+            }
+        }
+    }
+}
+
+/// Montgomery arithmetic modulo an odd 256-bit n, with R = 2²⁵⁶.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Montgomery256 {
+    n: U256,
+    /// −n⁻¹ mod 2⁶⁴, which is all that word-by-word reduction needs.
+    n_neg_inv: u64,
+    one: U256,
+    r2: U256,
+}
+
+impl Montgomery256 {
+    pub(crate) fn new(n: U256) -> Option<Self> {
+        if n.limbs[0].is_multiple_of(2) {
+            return None;
+        }
+        let n0 = n.limbs[0];
+        let mut inv = n0;
+        for _ in 0..5 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(n0.wrapping_mul(inv)));
+        }
+        let one = (U256::MAX % n + U256::ONE) % n;
+        let mut mont = Self { n, n_neg_inv: inv.wrapping_neg(), one, r2: U256::ZERO };
+        // Squaring the Montgomery form of 2 eight times gives that of 2²⁵⁶.
+        let mut r2 = mont.add(one, one);
+        for _ in 0..8 {
+            r2 = mont.mul(r2, r2);
+        }
+        mont.r2 = r2;
+        Some(mont)
+    }
+}
+
+impl MontgomeryArith for Montgomery256 {
+    type Int = U256;
+    fn modulus(&self) -> U256 {
+        self.n
+    }
+    fn one(&self) -> U256 {
+        self.one
+    }
+    fn to_montgomery(&self, a: U256) -> U256 {
+        self.mul(a % self.n, self.r2)
+    }
+    /// Coarsely integrated operand scanning: interleave one row of the
+    /// product with one word of the reduction.
+    fn mul(&self, a: U256, b: U256) -> U256 {
+        let n = &self.n.limbs;
+        let mut t = [0u64; 6];
+        for &bi in &b.limbs {
+            let mut carry: u128 = 0;
+            for (tj, &aj) in t.iter_mut().zip(&a.limbs) {
+                let cur = u128::from(*tj) + u128::from(aj) * u128::from(bi) + carry;
+                *tj = cur as u64;
+                carry = cur >> 64;
+            }
+            let cur = u128::from(t[4]) + carry;
+            t[4] = cur as u64;
+            t[5] = (cur >> 64) as u64;
+            let m = t[0].wrapping_mul(self.n_neg_inv);
+            let mut carry = (u128::from(t[0]) + u128::from(m) * u128::from(n[0])) >> 64;
+            for j in 1..4 {
+                let cur = u128::from(t[j]) + u128::from(m) * u128::from(n[j]) + carry;
+                t[j - 1] = cur as u64;
+                carry = cur >> 64;
+            }
+            let cur = u128::from(t[4]) + carry;
+            t[3] = cur as u64;
+            t[4] = t[5] + (cur >> 64) as u64;
+        }
+        let r = U256 { limbs: [t[0], t[1], t[2], t[3]] };
+        if t[4] != 0 || r >= self.n { r.overflowing_sub(self.n).0 } else { r }
+    }
+    fn add(&self, a: U256, b: U256) -> U256 {
+        let (sum, carry) = a.overflowing_add(b);
+        if carry || sum >= self.n { sum.overflowing_sub(self.n).0 } else { sum }
+    }
+    fn sub(&self, a: U256, b: U256) -> U256 {
+        let (diff, borrow) = a.overflowing_sub(b);
+        if borrow { diff.overflowing_add(self.n).0 } else { diff }
+    }
+    fn pow(&self, mut a: U256, exp: U256) -> U256 {
+        let mut result = self.one;
+        for bit in 0..256 - exp.leading_zeros() {
+            if (exp.limbs[bit as usize / 64] >> (bit % 64)) & 1 == 1 {
+                result = self.mul(result, a);
+            }
+            a = self.mul(a, a);
+        }
+        result
+    }
+}
+
+/// The number of rho steps tried on each of a few polynomials before a
+/// composite too wide for `u128` is given up on. Rho needs about √p steps
+/// to find a prime factor p, so this reaches factors of about 48 bits, and
+/// giving up takes about fifteen seconds.
+const WIDE_RHO_ITERATIONS: u128 = 1 << 24;
+
+/// Find a proper factor of a composite too wide for `u128`, with Pollard's
+/// rho, or `None` if its factors are too large to find within
+/// [`WIDE_RHO_ITERATIONS`] steps.
+pub(crate) fn find_factor(n: U256) -> Option<U256> {
+    find_factor_within(n, WIDE_RHO_ITERATIONS)
+}
+
+/// [`find_factor`] with the given budget of rho steps per polynomial.
+fn find_factor_within(n: U256, max_iterations: u128) -> Option<U256> {
+    if n.limbs[0].is_multiple_of(2) {
+        return Some(U256::from(2u8));
+    }
+    let mont = Montgomery256::new(n)?;
+    pollard::find_factor_mont_within(&mont, max_iterations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_factor_within, U256};
+    use crate::{Factorization, PrimeInt};
+
+    #[test]
+    fn test_u256_arithmetic() {
+        let a = U256::from_words(0x1234_5678_9abc_def0, u128::MAX - 12345);
+        let b = U256::from(0xfedc_ba98_7654_3210_0123_4567_89ab_cdefu128);
+        let (q, r) = a.checked_div_rem(b).unwrap();
+        assert_eq!(q * b + r, a);
+        assert!(r < b);
+        assert_eq!((a - b) + b, a);
+        assert_eq!(U256::from_product(u128::MAX, u128::MAX).to_words(), (u128::MAX - 1, 1));
+        assert_eq!(U256::MAX.checked_add(U256::ONE), None);
+        assert_eq!((a << 100) >> 100, a & (U256::MAX >> 100));
+        assert_eq!(U256::from_be_bytes(a.to_be_bytes()), a);
+        assert_eq!(U256::MAX.to_string(),
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935");
+        assert_eq!(U256::from(1234u128).to_string(), "1234");
+        assert_eq!(U256::from_product(1 << 100, 1 << 100).isqrt(), U256::from(1u128 << 100));
+        assert_eq!(U256::from_product(6 << 100, 9 << 80).gcd(U256::from(15u128 << 90)),
+            U256::from(3u128 << 90));
+    }
+
+    #[test]
+    fn test_u256_is_prime() {
+        // 2^255 - 19 and the largest 256-bit prime, 2^256 - 189
+        let p25519 = U256::from_words(1 << 127, 0) - U256::from(19u128);
+        assert!(p25519.is_prime());
+        assert!((U256::MAX - U256::from(188u128)).is_prime());
+        assert!(!(U256::MAX - U256::from(186u128)).is_prime());
+        assert_eq!(U256::MAX.prev_prime(), Some(U256::MAX - U256::from(188u128)));
+        assert_eq!(U256::from_words(1, 0).next_prime(), Some(U256::from_words(1, 51)));
+        assert_eq!(U256::MAX.next_prime(), None);
+        // Values that fit in u128 take the u128 path
+        assert!(U256::from((1u128 << 127) - 1).is_prime());
+        let (p, q) = ((1u128 << 127) - 1, 18446744073709551557);
+        assert!(!U256::from_product(p, q).is_prime());
+        assert!(!U256::from_product(p, p).is_prime());
+    }
+
+    #[test]
+    fn test_u256_factorize() {
+        // A product of two u128 values with factors that rho can reach
+        let a: u128 = 3 * 1000003 * 4294967291 * 1099511627791;
+        let b: u128 = (1 << 127) - 1;
+        let pf = Factorization::try_factorize(U256::from_product(a, b)).unwrap();
+        assert_eq!(pf.to_string(),
+            "3 * 1000003 * 4294967291 * 1099511627791 * 170141183460469231731687303715884105727");
+        assert_eq!(pf.value(), U256::from_product(a, b));
+        let pf = U256::from(600851475143u128).try_factorize().unwrap();
+        assert_eq!(pf.to_string(), "71 * 839 * 1471 * 6857");
+        let n = U256::from_product(1 << 100, 1 << 100) * U256::from(1099511627791u128);
+        assert_eq!(n.try_factorize().unwrap().to_string(), "2^200 * 1099511627791");
+        // Rho gives up on two large factors instead of searching forever
+        let (p, q) = ((1u128 << 127) - 1, (1u128 << 89) - 1);
+        assert_eq!(find_factor_within(U256::from_product(p, q), 1 << 10), None);
+    }
+
+    #[test]
+    #[ignore = "exhausts the rho budget, which takes a while"]
+    fn test_u256_try_factorize_out_of_reach() {
+        let (p, q) = ((1u128 << 127) - 1, (1u128 << 89) - 1);
+        assert_eq!(U256::from_product(p, q).try_factorize(), None);
+        let n = U256::from_product(p, q) * U256::from(6u8);
+        assert_eq!(Factorization::try_factorize(n), None);
+        assert_eq!(U256::from_product(p, 1 << 100).try_factorize().unwrap().to_string(),
+            format!("2^100 * {p}"));
+    }
+}
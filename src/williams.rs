@@ -0,0 +1,186 @@
+//! Williams' p+1 factoring method, built on Lucas sequences.
+//!
+//! The Lucas sequence V_k(A) = α^k + α^(-k), where α is a root of
+//! x² − A·x + 1, lives in the group of order p + 1 modulo a prime p whenever
+//! A² − 4 is a quadratic non-residue modulo p. If p + 1 divides k, then
+//! V_k(A) ≡ 2 (mod p), and a gcd with n reveals p. This mirrors Pollard's
+//! p−1 (see [`crate::pollard`]) with p + 1 in place of p − 1. Since A² − 4
+//! cannot be chosen as a non-residue for an unknown p, a few seeds A are
+//! tried; a seed that is a residue degrades to a p−1 search instead.
+//!
+//! Stage 1 uses the composition V_a(V_b(A)) = V_ab(A) to multiply in every
+//! prime power up to B1, and stage 2 uses a baby-step giant-step
+//! continuation over the primes in (B1, B2].
+//!
+//! Reference: <https://en.wikipedia.org/wiki/Williams%27s_p_%2B_1_algorithm>
+use crate::modular::{mod_mul, sub_mod};
+use crate::montgomery::Montgomery;
+use crate::pollard::stage1;
+use crate::sieve::SegmentedSieve;
+use crate::{u128_gcd, u128_is_prime};
+
+/// The seeds A tried in turn. Their discriminants A² − 4 are 5, 12, 32 and
+/// 77, which fall in different square classes, so for a given p at least
+/// one of them is likely to be a non-residue.
+const SEEDS: [u128; 4] = [3, 4, 6, 9];
+
+/// The giant step of stage 2. Every odd prime q can be written m·D ± j
+/// with j odd and below D/2.
+const STAGE2_WHEEL: u64 = 2310;
+
+/// Find a non-trivial factor of `n` using Williams' p+1 method with
+/// smoothness bounds `b1` for stage 1 and `b2` for stage 2.
+///
+/// Succeeds when some prime factor p of `n` has p + 1 composed of prime
+/// powers up to `b1` and at most one further prime up to `b2`. Returns
+/// `None` when `n` is 0, 1 or a prime, or when no factor was found within
+/// the bounds. The returned factor is not necessarily prime.
+#[must_use]
+pub fn williams_pp1(n: u128, b1: u64, b2: u64) -> Option<u128> {
+    if n < 4 || u128_is_prime(n) {
+        return None;
+    }
+    find_factor(n, b1, b2)
+}
+
+/// Run both stages of p+1 on the composite `n` for each seed in turn.
+pub(crate) fn find_factor(n: u128, b1: u64, b2: u64) -> Option<u128> {
+    debug_assert!(n >= 4);
+    if n.is_multiple_of(2) {
+        return Some(2);
+    }
+    let mont = Montgomery::new(n)?;
+    let two = mont.to_montgomery(2);
+    let mul = |x, y| mont.mul(x, y);
+    let sub = |x, y| mont.sub(x, y);
+    SEEDS.iter().find_map(|&a| {
+        let seed = mont.to_montgomery(a);
+        match stage1(n, b1, seed, two, |v, k| lucas_ladder(v, k, two, mul, sub)) {
+            Ok(v) => stage2(&mont, v, b1, b2),
+            Err(g) => (g < n).then_some(g),
+        }
+    })
+}
+
+/// Compute the Lucas sequence term V_k(a) modulo `m`, where V_0 = 2,
+/// V_1 = a and V_(i+1) = a·V_i − V_(i−1).
+#[must_use]
+pub fn lucas_v(a: u128, k: u128, m: u128) -> u128 {
+    if m == 1 {
+        return 0;
+    }
+    if let Some(mont) = Montgomery::new(m) {
+        let (a, two) = (mont.to_montgomery(a), mont.to_montgomery(2));
+        let v = lucas_ladder(a, k, two, |x, y| mont.mul(x, y), |x, y| mont.sub(x, y));
+        return mont.from_montgomery(v);
+    }
+    lucas_ladder(a % m, k, 2 % m, |x, y| mod_mul(x, y, m), |x, y| sub_mod(x, y, m))
+}
+
+/// The ladder behind [`lucas_v`], over the bits of `k`, for any
+/// representation of the residues given by `two`, `mul` and `sub`. It
+/// keeps the pair (V_i, V_(i+1)) and applies V_2i = V_i² − 2 and
+/// V_(2i+1) = V_i·V_(i+1) − a.
+fn lucas_ladder(
+    a: u128,
+    k: u128,
+    two: u128,
+    mul: impl Fn(u128, u128) -> u128,
+    sub: impl Fn(u128, u128) -> u128,
+) -> u128 {
+    if k == 0 {
+        return two;
+    }
+    let (mut v0, mut v1) = (a, sub(mul(a, a), two));
+    for bit in (0..127 - k.leading_zeros()).rev() {
+        let cross = sub(mul(v0, v1), a);
+        if (k >> bit) & 1 == 1 {
+            v0 = cross;
+            v1 = sub(mul(v1, v1), two);
+        } else {
+            v1 = cross;
+            v0 = sub(mul(v0, v0), two);
+        }
+    }
+    v0
+}
+
+/// Stage 2 of p+1: for every prime q = m·D ± j in (b1, b2], multiply in
+/// V_mD − V_j, which vanishes modulo p whenever α^(mD + j) or α^(mD − j)
+/// is 1 modulo p, so a single prime q dividing p + 1 is caught.
+fn stage2(mont: &Montgomery, v: u128, b1: u64, b2: u64) -> Option<u128> {
+    let n = mont.modulus();
+    let d = STAGE2_WHEEL;
+    let m = |a, b| mont.mul(a, b);
+    let s = |a, b| mont.sub(a, b);
+    let two = mont.add(mont.one(), mont.one());
+    let ladder = |k: u64| lucas_ladder(v, u128::from(k), two, m, s);
+    let mut primes = SegmentedSieve::new(b1.max(2).saturating_add(1)..b2.saturating_add(1)).peekable();
+    let first = *primes.peek()?;
+    // Baby steps: V_j for every odd j below D/2, via V_(j+2) = V_j·V_2 − V_(j−2).
+    let v2 = s(m(v, v), two);
+    let mut baby = vec![v; (d as usize / 2).div_ceil(2)];
+    baby[1] = s(m(v, v2), v);
+    for i in 2..baby.len() {
+        baby[i] = s(m(baby[i - 1], v2), baby[i - 2]);
+    }
+    // Giant steps: V_mD, via V_(m+1)D = V_mD·V_D − V_(m−1)D.
+    let vd = ladder(d);
+    let mut g = (first + d / 2) / d;
+    let mut cur = ladder(g * d);
+    let mut next = ladder((g + 1) * d);
+    let mut acc = mont.one();
+    for q in primes {
+        let qm = (q + d / 2) / d;
+        while g < qm {
+            (cur, next) = (next, s(m(next, vd), cur));
+            g += 1;
+        }
+        let j = q.abs_diff(qm * d);
+        acc = m(acc, s(cur, baby[j as usize / 2]));
+    }
+    let g = u128_gcd(acc, n);
+    (g > 1 && g < n).then_some(g)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lucas_v, williams_pp1};
+
+    #[test]
+    fn test_lucas_v() {
+        // V_k(3) with Q = 1: 2, 3, 7, 18, 47, 123, 322, ...
+        let expected: [u128; 7] = [2, 3, 7, 18, 47, 123, 322];
+        for (k, &v) in expected.iter().enumerate() {
+            assert_eq!(lucas_v(3, k as u128, u128::MAX), v);
+            assert_eq!(lucas_v(3, k as u128, 11), v % 11);
+        }
+        // Composition: V_6(A) = V_2(V_3(A))
+        let m: u128 = 1000000007;
+        assert_eq!(lucas_v(lucas_v(5, 3, m), 2, m), lucas_v(5, 6, m));
+    }
+
+    #[test]
+    fn test_williams_pp1_prime_powers() {
+        // p + 1 is smooth, but V_k − 2 is a square times Δ, so a power of p
+        // comes out squared: p² itself is not split, though p²·r is
+        let p: u128 = 3926185272901;
+        assert_eq!(williams_pp1(p * p, 1_000, 1_000), None);
+        assert_eq!(williams_pp1(p * p * 1000003, 1_000, 1_000), Some(p * p));
+        let q: u128 = 18446744073709551557;
+        assert_eq!(williams_pp1(q * q, 1_000, 50_000), None);
+    }
+
+    #[test]
+    fn test_williams_pp1_smooth_factors() {
+        // 2^64 - 59 has p + 1 = 2 * 3 * 7 * 439208192231179799
+        let q: u128 = 18446744073709551557;
+        // p + 1 = 2 * 179 * 181 * 251 * 283 * 853, found in stage 1
+        let p: u128 = 3926185272901;
+        assert_eq!(williams_pp1(p * q, 1_000, 1_000), Some(p));
+        // p + 1 = 2 * 61 * 383 * 557 * 839 * 30011, needs stage 2
+        let p: u128 = 655324232419477;
+        assert_eq!(williams_pp1(p * q, 1_000, 1_000), None);
+        assert_eq!(williams_pp1(p * q, 1_000, 50_000), Some(p));
+    }
+}
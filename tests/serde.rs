@@ -0,0 +1,104 @@
+#![cfg(feature = "serde")]
+use primefactor::candidates::PrimeWheel210;
+use primefactor::{
+    DescendingPrimes,
+    Factorization,
+    IntFactor,
+    PrimeFactors,
+    PrimeInt,
+    PrimeNumbers,
+    Primes,
+    U256};
+
+#[test]
+fn test_serde_factorizations() {
+    let pf = PrimeFactors::factorize(u128::MAX - 1);
+    let json = serde_json::to_string(&pf).unwrap();
+    assert!(json.starts_with(r#"[{"integer":"2","exponent":1},"#), "{json}");
+    assert_eq!(serde_json::from_str::<PrimeFactors>(&json).unwrap(), pf);
+    let bytes = bincode::serialize(&pf).unwrap();
+    assert_eq!(bincode::deserialize::<PrimeFactors>(&bytes).unwrap(), pf);
+    let pf = Factorization::<u32>::factorize(5040);
+    let bytes = bincode::serialize(&pf).unwrap();
+    assert_eq!(bincode::deserialize::<Factorization<u32>>(&bytes).unwrap(), pf);
+    let f: IntFactor = serde_json::from_str(r#"{"integer":"3","exponent":4}"#).unwrap();
+    assert_eq!(f.to_string(), "3^4");
+    // Plain numbers are accepted where they fit
+    let f: IntFactor = serde_json::from_str(r#"{"integer":3,"exponent":4}"#).unwrap();
+    assert_eq!(f.to_string(), "3^4");
+    assert_eq!(serde_json::from_str::<PrimeFactors>("[]").unwrap().value(), 1);
+    let n = U256::from(u128::MAX) * U256::from(3u128);
+    let pf = n.try_factorize().unwrap();
+    let bytes = bincode::serialize(&pf).unwrap();
+    assert_eq!(bincode::deserialize::<Factorization<U256>>(&bytes).unwrap(), pf);
+}
+
+#[test]
+fn test_serde_rejects_invalid_factorizations() {
+    let err = |json: &str| serde_json::from_str::<PrimeFactors>(json).unwrap_err().to_string();
+    assert!(err(r#"[{"integer":"4","exponent":1}]"#).contains("4 is not prime"));
+    assert!(err(r#"[{"integer":"2","exponent":0}]"#).contains("invalid exponent"));
+    assert!(err(r#"[{"integer":"5","exponent":1},{"integer":"3","exponent":1}]"#).contains("ascending"));
+    assert!(err(r#"[{"integer":"2","exponent":64},{"integer":"3","exponent":41}]"#).contains("does not fit"));
+    assert!(err(r#"[{"integer":"x","exponent":1}]"#).contains("invalid value"));
+    assert!(serde_json::from_str::<Factorization<u32>>(r#"[{"integer":"4294967311","exponent":1}]"#).is_err());
+    let bytes = bincode::serialize(&(1u64, 91u128, 1u32)).unwrap();
+    assert!(bincode::deserialize::<PrimeFactors>(&bytes).is_err());
+}
+
+#[test]
+fn test_serde_wheel() {
+    let mut wheel = PrimeWheel210::from(1000);
+    for _ in 0..100 {
+        let json = serde_json::to_string(&wheel).unwrap();
+        let mut restored: PrimeWheel210 = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.next(), wheel.next());
+    }
+    for wheel in [PrimeWheel210::new(), PrimeWheel210::from(4), PrimeWheel210::from(u128::MAX - 300)] {
+        let bytes = bincode::serialize(&wheel).unwrap();
+        assert_eq!(bincode::deserialize::<PrimeWheel210>(&bytes).unwrap(), wheel);
+    }
+    assert!(serde_json::from_str::<PrimeWheel210>(r#"{"base":"12","index":5}"#).is_err());
+    assert!(serde_json::from_str::<PrimeWheel210>(r#"{"base":"3","index":1}"#).is_err());
+    assert!(serde_json::from_str::<PrimeWheel210>(r#"{"base":"221","index":53}"#).is_err());
+    assert!(serde_json::from_str::<PrimeWheel210>(r#"{"base":"431","index":5}"#).is_ok());
+}
+
+#[test]
+fn test_serde_resume_prime_iterators() {
+    // Resume at every stage: wheel, sieve, and above the sieve ceiling
+    for (start, ceiling) in [(0, 1 << 40), (1000, 1 << 40), (1_000_000, 1_001_000), (1 << 50, 1 << 40)] {
+        let mut primes = PrimeNumbers::from(start).sieve_ceiling(ceiling);
+        let all: Vec<u128> = primes.clone().take(300).collect();
+        let mut resumed = Vec::new();
+        for _ in 0..30 {
+            let json = serde_json::to_string(&primes).unwrap();
+            primes = serde_json::from_str(&json).unwrap();
+            resumed.extend(primes.by_ref().take(10));
+        }
+        assert_eq!(resumed, all, "from {start}");
+    }
+    // Resume in the middle of the sieve, and at its end
+    for (skip, ceiling) in [(1000, 1 << 40), (200, 1400)] {
+        let mut primes = PrimeNumbers::new().sieve_ceiling(ceiling);
+        primes.by_ref().take(skip).for_each(drop);
+        let json = serde_json::to_string(&primes).unwrap();
+        let restored: PrimeNumbers = serde_json::from_str(&json).unwrap();
+        assert!(restored.take(1000).eq(primes.take(1000)), "{json}");
+    }
+    let mut primes = Primes::<u32>::from(4_294_967_000);
+    primes.by_ref().take(5).for_each(drop);
+    let bytes = bincode::serialize(&primes).unwrap();
+    let restored: Primes<u32> = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(restored.collect::<Vec<_>>(), primes.collect::<Vec<_>>());
+
+    let mut descending = DescendingPrimes::from(1000);
+    let all: Vec<u128> = descending.clone().collect();
+    let mut resumed = Vec::new();
+    for _ in 0..all.len() + 2 {
+        let bytes = bincode::serialize(&descending).unwrap();
+        descending = bincode::deserialize(&bytes).unwrap();
+        resumed.extend(descending.next());
+    }
+    assert_eq!(resumed, all);
+}
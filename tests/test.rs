@@ -4,6 +4,9 @@ use primefactor::{
     primefactor_gcd,
     PrimeFactors,
     PrimeNumbers,
+    Primes,
+    prime_pi,
+    SignedFactors,
     u128_gcd,
     u128_is_prime,
     u128_lcm};
@@ -96,6 +99,34 @@ fn test_a_few_lcm() {
     assert_eq!(u128_lcm(0, 0), 0);
 }
 
+#[test]
+fn test_factorize_signed() {
+    let pf = PrimeFactors::factorize_signed(-40);
+    assert_eq!(pf.to_string(), "-1 * 2^3 * 5");
+    assert_eq!(pf.value(), -40);
+    assert!(pf.is_negative());
+    assert_eq!(pf.abs(), &PrimeFactors::factorize(40));
+    assert_eq!(PrimeFactors::factorize_signed(40).to_string(), "2^3 * 5");
+    assert_eq!(PrimeFactors::factorize_signed(-1).to_string(), "-1");
+    assert_eq!(PrimeFactors::factorize_signed(-1).value(), -1);
+    assert_eq!(PrimeFactors::factorize_signed(1).value(), 1);
+    assert_eq!(PrimeFactors::factorize_signed(i128::MIN).to_string(), "-1 * 2^127");
+    for n in [i128::MIN, i128::MAX, -i128::MAX, -600851475143] {
+        assert_eq!(PrimeFactors::factorize_signed(n).value(), n);
+    }
+    // gcd and lcm ignore the signs
+    let a = PrimeFactors::factorize_signed(-2*2*3*5*7);
+    let b = PrimeFactors::factorize_signed(2*5*5*11);
+    assert_eq!(a.gcd(&b), PrimeFactors::factorize(2*5));
+    assert_eq!(a.lcm(&b), PrimeFactors::factorize(2*2*3*5*5*7*11));
+    assert_eq!(b.lcm(&a), a.lcm(&b));
+    assert_eq!(SignedFactors::from(PrimeFactors::factorize(12)), PrimeFactors::factorize_signed(12));
+    for (x, y) in [(3*4*5, 3*4*7), (27*64*121, 9*32*49), (3*7*13, 2*5*11), (1, 12)] {
+        let pf = PrimeFactors::factorize(x).lcm(&PrimeFactors::factorize(y));
+        assert_eq!(pf.value(), u128_lcm(x, y));
+    }
+}
+
 #[test]
 fn test_some_gcd_lcm() {
     (0..10).into_par_iter().for_each(|_| {
@@ -180,11 +211,83 @@ fn test_prime_numbers_iterator() {
 }
 
 #[test]
-#[ignore] // Very slow: trial-division fallback for primes above the MR threshold
+fn test_prime_numbers_sieve_ceiling() {
+    // The sieve hands back to the wheel at the ceiling
+    for ceiling in [0, 1000, 7919, 100_003, 1 << 20] {
+        let primes: Vec<u128> = PrimeNumbers::new().sieve_ceiling(ceiling).take(100_000).collect();
+        assert_eq!(primes.len(), 100_000);
+        assert_eq!(primes[99_999], nth_prime(99_999) as u128, "ceiling {ceiling}");
+        assert!(primes.windows(2).all(|w| w[0] < w[1] && u128_is_prime(w[1])));
+    }
+    let start = 1_000_000_000_000u128;
+    let sieved: Vec<u128> = PrimeNumbers::from(start).take(1000).collect();
+    let tested: Vec<u128> = PrimeNumbers::from(start).sieve_ceiling(0).take(1000).collect();
+    assert_eq!(sieved, tested);
+    // The sieve stops at the end of the type
+    assert_eq!(Primes::<u32>::from(4_294_000_000).count(), 43_362);
+    assert_eq!(Primes::<u32>::from(4_294_000_000).last(), Some(4294967291));
+}
+
+#[test]
+fn test_prime_pi_against_iterator() {
+    // Above the sieve limit, counted with the Meissel-Lehmer method
+    let base = 1u128 << 25;
+    let below = prime_pi(base).unwrap();
+    let mut count = below;
+    for p in PrimeNumbers::from(base + 1).take_while(|&p| p <= base + 100_000) {
+        count += 1;
+        if count.is_multiple_of(1000) {
+            assert_eq!(prime_pi(p), Some(count), "pi({p})");
+            assert_eq!(prime_pi(p - 1), Some(count - 1), "pi({})", p - 1);
+        }
+    }
+    assert_eq!(below, PrimeNumbers::new().take_while(|&p| p <= base).count() as u128);
+}
+
+#[test]
+fn test_nth_prime_against_reikna() {
+    // reikna counts from 0
+    for k in [1u64, 2, 1000, 1_077_871, 1_077_872, 2_000_000, 3_141_592] {
+        assert_eq!(primefactor::nth_prime(k.into()), Some(nth_prime(k - 1) as u128), "p({k})");
+    }
+    for k in [5_000_000u128, 20_000_001] {
+        let p = primefactor::nth_prime(k).unwrap();
+        assert!(u128_is_prime(p));
+        assert_eq!(prime_pi(p), Some(k));
+    }
+}
+
+#[test]
 fn test_large_primes_above_mr_threshold() {
+    use primefactor::certificate::PrimalityCertificate;
     assert!(u128_is_prime(618970019642690137449562111));             // 2^89 - 1
     assert!(u128_is_prime(162259276829213363391578010288127));       // 2^107 - 1
     assert!(u128_is_prime(170141183460469231731687303715884105727)); // 2^127 - 1
+    assert!(!u128_is_prime(3317044064679887385961981));              // strong pseudoprime
+    let cert = PrimalityCertificate::new(170141183460469231731687303715884105727).unwrap();
+    assert!(cert.verify());
+    // A 128-bit prime and a product of two primes just below 2^64
+    assert!(u128_is_prime(340282366920938463463374607431768211297));
+    assert!(!u128_is_prime(18446744073709551557 * 18446744073709551533));
+}
+
+#[test]
+fn test_large_primes_with_bpsw() {
+    use primefactor::factorizer::Factorizer;
+    use primefactor::{u128_is_prime_with, PrimalityTest};
+    let mersenne: [u128; 3] = [(1 << 89) - 1, (1 << 107) - 1, (1 << 127) - 1];
+    for p in mersenne {
+        assert!(u128_is_prime_with(p, PrimalityTest::Bpsw), "{p} is prime");
+        assert!(!u128_is_prime_with(p - 2, PrimalityTest::Bpsw), "{} is composite", p - 2);
+    }
+    // 2^31 - 1 is found by p-1, leaving 2^89 - 1 to the primality test
+    let factorizer = Factorizer::builder()
+        .primality_test(PrimalityTest::Bpsw)
+        .method(primefactor::factorizer::PollardPm1 { b1: 1_000, b2: 50_000 })
+        .build();
+    let (p, q): (u128, u128) = ((1 << 31) - 1, (1 << 89) - 1);
+    assert_eq!(factorizer.factorize(p * q).to_vec(), vec![p, q]);
+    assert_eq!(factorizer.factorize(7 * q).to_vec(), vec![7, q]);
 }
 
 #[test]
@@ -266,6 +369,99 @@ fn test_primefactors_methods() {
     assert_eq!(prime_pf.to_string(), "13");
 }
 
+#[test]
+fn test_primefactors_arithmetic_functions() {
+    // Compare with the definitions for small n
+    for n in 1..1000u128 {
+        let pf = PrimeFactors::factorize(n);
+        let divisors: Vec<u128> = (1..=n).filter(|d| n % d == 0).collect();
+        assert_eq!(pf.divisor_count(), divisors.len() as u128, "tau({n})");
+        assert_eq!(pf.divisor_sum(0), divisors.len() as u128, "sigma_0({n})");
+        assert_eq!(pf.divisor_sum(1), divisors.iter().sum::<u128>(), "sigma({n})");
+        assert_eq!(pf.divisor_sum(2), divisors.iter().map(|d| d * d).sum::<u128>(), "sigma_2({n})");
+        let units: Vec<u128> = (1..=n).filter(|&a| u128_gcd(a, n) == 1).collect();
+        assert_eq!(pf.euler_phi(), units.len() as u128, "phi({n})");
+        // λ(n) is the least m with a^m ≡ 1 for every unit a
+        let lambda = (1..=n)
+            .find(|&m| units.iter().all(|&a| (0..m).fold(1, |acc, _| acc * a % n) == 1 % n))
+            .unwrap();
+        assert_eq!(pf.carmichael_lambda(), lambda, "lambda({n})");
+        let squarefree = (2..=n.isqrt()).all(|d| n % (d * d) != 0);
+        assert_eq!(pf.is_squarefree(), squarefree, "{n}");
+        assert_eq!(pf.radical(), pf.factors().iter().map(|f| f.integer).product(), "rad({n})");
+        let liouville = if pf.count_factors().is_multiple_of(2) { 1 } else { -1 };
+        assert_eq!(pf.liouville(), liouville, "{n}");
+        assert_eq!(pf.mobius(), if squarefree { liouville } else { 0 }, "mu({n})");
+    }
+    let pf = PrimeFactors::factorize(72);
+    assert!(pf.is_squarefull());
+    assert!(!PrimeFactors::factorize(12).is_squarefull());
+    assert!(PrimeFactors::factorize(1).is_squarefull());
+    // The checked variants report overflow near the top of the type
+    let pf = PrimeFactors::factorize(u128::MAX);
+    assert_eq!(pf.divisor_count(), 512);
+    assert_eq!(pf.radical(), u128::MAX);
+    assert_eq!(pf.checked_divisor_sum(1), None);
+    assert_eq!(pf.checked_divisor_sum(0), Some(512));
+    let p = 340282366920938463463374607431768211297;
+    assert_eq!(PrimeFactors::factorize(p).euler_phi(), p - 1);
+    assert_eq!(PrimeFactors::factorize(p).divisor_sum(1), p + 1);
+    assert_eq!(PrimeFactors::factorize(p).checked_divisor_sum(2), None);
+    assert_eq!(PrimeFactors::factorize(1 << 127).carmichael_lambda(), 1 << 125);
+    assert_eq!(primefactor::Factorization::<u32>::factorize(5040).divisor_sum(1), 19344);
+}
+
+#[test]
+fn test_primefactors_divisors() {
+    for n in 1..1000u128 {
+        let pf = PrimeFactors::factorize(n);
+        let expected: Vec<u128> = (1..=n).filter(|d| n % d == 0).collect();
+        let mut divisors: Vec<u128> = pf.divisors().collect();
+        divisors.sort_unstable();
+        assert_eq!(divisors, expected, "{n}");
+        assert_eq!(pf.sorted_divisors(), expected, "{n}");
+        let mut proper: Vec<u128> = pf.proper_divisors().collect();
+        proper.sort_unstable();
+        assert_eq!(proper, expected[..expected.len() - 1], "{n}");
+        assert_eq!(pf.proper_divisor_count(), proper.len() as u128, "{n}");
+        assert_eq!(pf.proper_divisor_sum(), proper.iter().sum::<u128>(), "{n}");
+    }
+    // A highly composite value near the top of u128, without overflow
+    let pf = PrimeFactors::factorize(u128::MAX);
+    assert_eq!(pf.divisors().count(), 512);
+    assert_eq!(pf.proper_divisors().max(), Some(u128::MAX / 3));
+    assert_eq!(pf.sorted_divisors().last(), Some(&u128::MAX));
+    assert_eq!(pf.checked_proper_divisor_sum(), None);
+    assert_eq!(PrimeFactors::factorize(28).proper_divisor_sum(), 28);
+}
+
+#[test]
+fn test_parse_factorizations() {
+    use primefactor::{Factorization, IntFactor, ParseFactorsError};
+    for n in [1u128, 2, 40, 5040, 1 << 127, u128::MAX, 340282366920938463463374607431768211297] {
+        let pf = PrimeFactors::factorize(n);
+        assert_eq!(pf.to_string().parse::<PrimeFactors>(), Ok(pf.clone()), "{n}");
+        for f in pf.factors() {
+            assert_eq!(f.to_string().parse::<IntFactor>(), Ok(*f));
+        }
+    }
+    assert_eq!(" 2^3*5 *  7 ".parse::<PrimeFactors>().map(|pf| pf.value()), Ok(280));
+    assert_eq!("2^1".parse::<IntFactor>().map(|f| f.exponent), Ok(1));
+    let err = |s: &str| s.parse::<PrimeFactors>().unwrap_err();
+    assert_eq!(err("2 * 4"), ParseFactorsError::NotPrime("4".into()));
+    assert_eq!(err("1"), ParseFactorsError::NotPrime("1".into()));
+    assert_eq!(err("5 * 3"), ParseFactorsError::NotAscending("3".into()));
+    assert_eq!(err("3 * 3"), ParseFactorsError::NotAscending("3".into()));
+    assert_eq!(err("2^0"), ParseFactorsError::InvalidExponent("2^0".into()));
+    assert_eq!(err("2^x"), ParseFactorsError::InvalidExponent("2^x".into()));
+    assert_eq!(err("2 * * 3"), ParseFactorsError::InvalidNumber("".into()));
+    assert_eq!(err("-2"), ParseFactorsError::InvalidNumber("-2".into()));
+    assert_eq!(err("2^128"), ParseFactorsError::Overflow);
+    assert_eq!(err("2^64 * 3^41"), ParseFactorsError::Overflow);
+    assert_eq!("4294967311".parse::<Factorization<u32>>(), Err(ParseFactorsError::InvalidNumber("4294967311".into())));
+    assert_eq!(err("2 * 4").to_string(), "4 is not prime");
+}
+
 #[test]
 fn test_intfactor_methods() {
     let f = primefactor::IntFactor { integer: 7, exponent: 3 };
@@ -286,3 +482,111 @@ fn test_primewheel30() {
         assert_eq!(wheel.next().unwrap(), exp);
     }
 }
+
+#[test]
+fn test_factorize_semiprimes_without_small_factors() {
+    let cases: [(u128, u128); 3] = [
+        (1000003, 1000033),
+        (4294967279, 4294967291),
+        (1000000007, 998244353),
+    ];
+    for (p, q) in cases {
+        let pf = PrimeFactors::factorize(p * q);
+        assert_eq!(pf.to_vec(), vec![p.min(q), p.max(q)], "factorize({})", p * q);
+    }
+    // Three ~30-bit primes, well above 64 bits in total
+    let n: u128 = 998244353 * 1000000007 * 1000000009;
+    assert_eq!(PrimeFactors::factorize(n).to_vec(), vec![998244353, 1000000007, 1000000009]);
+    // Mixed small factors, repeated large factors
+    let n: u128 = 2 * 2 * 3 * 4294967291 * 4294967291 * 65537;
+    assert_eq!(PrimeFactors::factorize(n).to_string(), "2^2 * 3 * 65537 * 4294967291^2");
+}
+
+#[test]
+fn test_factorize_random_64bit_numbers() {
+    let mut rnd = rand::rng();
+    for _ in 0..200 {
+        let num = rnd.random_range(2..=u64::MAX as u128);
+        let facts = PrimeFactors::factorize(num);
+        assert_eq!(facts.value(), num);
+        for f in &facts {
+            assert!(u128_is_prime(f.integer), "{} in {num} = {facts} is not prime", f.integer);
+        }
+    }
+}
+
+#[test]
+fn test_factorize_ecm() {
+    use primefactor::ecm::EcmParams;
+    let params = EcmParams::default();
+    let n: u128 = 2 * 3 * 1000000007 * 2305843009213693951;
+    let pf = PrimeFactors::factorize_ecm(n, &params);
+    assert_eq!(pf, PrimeFactors::factorize(n));
+    assert_eq!(pf.to_vec(), vec![2, 3, 1000000007, 2305843009213693951]);
+    // Too little effort for ECM still yields a complete factorization
+    let pf = PrimeFactors::factorize_ecm(1000003 * 1000033, &EcmParams::new(10, 10, 1));
+    assert_eq!(pf.to_vec(), vec![1000003, 1000033]);
+    assert!(PrimeFactors::factorize_ecm(1, &params).is_empty());
+    assert!(PrimeFactors::factorize_ecm(13, &params).is_prime());
+}
+
+#[test]
+fn test_factorize_balanced_semiprimes_with_siqs() {
+    // Two 64-bit primes: far beyond trial division and Pollard's rho, and
+    // too far apart for Fermat's method
+    let (p, q): (u128, u128) = (13835058055282163729, 18446744073709551557);
+    assert_eq!(PrimeFactors::factorize(p * q).to_vec(), vec![p, q]);
+    let n: u128 = 3 * 5 * 1099511627791 * 1649267441681;
+    assert_eq!(PrimeFactors::factorize(n).to_string(), "3 * 5 * 1099511627791 * 1649267441681");
+}
+
+#[test]
+fn test_factorize_smooth_factor_with_pm1() {
+    use primefactor::pollard::pollard_pm1;
+    // p - 1 = 2^2 * 31 * 41 * 47 * 137 * 149 * 229
+    let (p, q): (u128, u128) = (1116980824997, 18446744073709551557);
+    assert_eq!(pollard_pm1(p * q, 1_000, 50_000), Some(p));
+    assert_eq!(PrimeFactors::factorize(p * q).to_vec(), vec![p, q]);
+}
+
+#[test]
+fn test_factorize_close_factors_with_fermat() {
+    use primefactor::fermat::fermat;
+    // Two 64-bit primes 24 apart, as in a badly generated RSA key
+    let (p, q): (u128, u128) = (18446744073709551533, 18446744073709551557);
+    assert_eq!(fermat(p * q, 1), Some(p));
+    assert_eq!(PrimeFactors::factorize(p * q).to_vec(), vec![p, q]);
+    let n: u128 = 3 * 5 * 1099511627791 * 1099511627803;
+    assert_eq!(PrimeFactors::factorize(n).to_string(), "3 * 5 * 1099511627791 * 1099511627803");
+    let n: u128 = 4294967291 * 4294967291 * 4294967279 * 4294967279;
+    assert_eq!(PrimeFactors::factorize(n).to_string(), "4294967279^2 * 4294967291^2");
+}
+
+#[test]
+fn test_factor_methods_on_trivial_inputs() {
+    use primefactor::{ecm, fermat, pollard, siqs, squfof, williams};
+    type Method<'a> = &'a dyn Fn(u128) -> Option<u128>;
+    let params = ecm::EcmParams::default();
+    let methods: [(&str, Method); 8] = [
+        ("pollard_rho", &pollard::pollard_rho),
+        ("pollard_pm1", &|n| pollard::pollard_pm1(n, 1_000, 50_000)),
+        ("williams_pp1", &|n| williams::williams_pp1(n, 1_000, 50_000)),
+        ("ecm", &|n| ecm::ecm(n, &params)),
+        ("squfof", &squfof::squfof),
+        ("fermat", &|n| fermat::fermat(n, 100)),
+        ("lehman", &fermat::lehman),
+        ("siqs", &siqs::siqs),
+    ];
+    for (name, method) in methods {
+        // Neither 0, 1 nor a prime has a proper factor
+        for n in [0, 1, 2, 3, 5, 7, 13, 4294967291, 18446744073709551557] {
+            assert_eq!(method(n), None, "{name}({n})");
+        }
+        // Any factor of a small composite is a proper one
+        for n in [4, 6, 9, 15, 25, 49] {
+            if let Some(d) = method(n) {
+                assert!(d > 1 && d < n && n % d == 0, "{name}({n}) = {d}");
+            }
+        }
+    }
+}